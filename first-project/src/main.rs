@@ -2,6 +2,7 @@ use bevy::{
     core::FixedTimestep,
     prelude::*,
     sprite::collide_aabb::{collide, Collision},
+    window::WindowResized,
 };
 
 use rand::prelude::random;
@@ -27,6 +28,7 @@ fn main() {
                 .with_system(paddle_movement_system.system())
         )
         .add_system(scoreboard_system.system())
+        .add_system(window_resize_system.system())
         .run();
 }
 
@@ -48,6 +50,24 @@ enum Collider {
     Paddle,
 }
 
+enum Wall {
+    Left,
+    Right,
+    Bottom,
+    Top,
+}
+
+impl Wall {
+    fn layout(&self, bounds: Vec2, thickness: f32) -> (Vec3, Vec2) {
+        match self {
+            Wall::Left => (Vec3::new(-bounds.x / 2.0, 0.0, 0.0), Vec2::new(thickness, bounds.y + thickness)),
+            Wall::Right => (Vec3::new(bounds.x / 2.0, 0.0, 0.0), Vec2::new(thickness, bounds.y + thickness)),
+            Wall::Bottom => (Vec3::new(0.0, -bounds.y / 2.0, 0.0), Vec2::new(bounds.x + thickness, thickness)),
+            Wall::Top => (Vec3::new(0.0, bounds.y / 2.0, 0.0), Vec2::new(bounds.x + thickness, thickness)),
+        }
+    }
+}
+
 fn setup(
     mut commands: Commands,
     mut materials: ResMut<Assets<ColorMaterial>>,
@@ -159,29 +179,56 @@ fn setup_walls(
     let wall_thickness = 20.0;
     let bounds = Vec2::new(window.width(), window.height());
 
-    let transforms = vec![
-        Transform::from_xyz(-bounds.x / 2.0, 0.0, 0.0),
-        Transform::from_xyz(bounds.x / 2.0, 0.0, 0.0),
-        Transform::from_xyz(0.0, -bounds.y / 2.0, 0.0),
-        Transform::from_xyz(0.0, bounds.y / 2.0, 0.0),
-    ];
-
-    let sprites = vec![
-        Sprite::new(Vec2::new(wall_thickness, bounds.y + wall_thickness)),
-        Sprite::new(Vec2::new(wall_thickness, bounds.y + wall_thickness)),
-        Sprite::new(Vec2::new(bounds.x + wall_thickness, wall_thickness)),
-        Sprite::new(Vec2::new(bounds.x + wall_thickness, wall_thickness)),
-    ];
-
-    for (transform, sprite) in transforms.into_iter().zip(sprites.into_iter()) {
+    for wall in vec![Wall::Left, Wall::Right, Wall::Bottom, Wall::Top] {
+        let (translation, size) = wall.layout(bounds, wall_thickness);
         commands
             .spawn_bundle(SpriteBundle {
                 material: wall_material.clone(),
-                transform: transform,
-                sprite: sprite,
+                transform: Transform::from_translation(translation),
+                sprite: Sprite::new(size),
                 ..Default::default()
             })
-            .insert(Collider::Solid);
+            .insert(Collider::Solid)
+            .insert(wall);
+    }
+}
+
+fn window_resize_system(
+    mut resize_events: EventReader<WindowResized>,
+    windows: Res<Windows>,
+    mut wall_query: Query<(&Wall, &mut Sprite, &mut Transform)>,
+    mut movable_query: Query<(&Sprite, &mut Transform), (Or<(With<Ball>, With<Paddle>)>, Without<Wall>)>,
+    brick_query: Query<(Entity, &Collider, &Transform), (Without<Ball>, Without<Paddle>, Without<Wall>)>,
+    mut commands: Commands,
+) {
+    let window = windows.get_primary().unwrap();
+    if !resize_events.iter().any(|event| event.id == window.id()) {
+        return;
+    }
+    let wall_thickness = 20.0;
+    let bounds = Vec2::new(window.width(), window.height());
+
+    for (wall, mut sprite, mut transform) in wall_query.iter_mut() {
+        let (translation, size) = wall.layout(bounds, wall_thickness);
+        transform.translation = translation;
+        sprite.size = size;
+    }
+
+    // keep the ball and the paddle inside the shrunken walls
+    for (sprite, mut transform) in movable_query.iter_mut() {
+        let xmax = bounds.x * 0.5 - wall_thickness * 0.5 - sprite.size.x * 0.5;
+        let ymax = bounds.y * 0.5 - wall_thickness * 0.5 - sprite.size.y * 0.5;
+        transform.translation.x = transform.translation.x.min(xmax).max(-xmax);
+        transform.translation.y = transform.translation.y.min(ymax).max(-ymax);
+    }
+
+    // bricks left outside the walls can no longer be reached
+    for (entity, collider, transform) in brick_query.iter() {
+        if let Collider::Scorable = *collider {
+            if transform.translation.x.abs() > bounds.x * 0.5 || transform.translation.y.abs() > bounds.y * 0.5 {
+                commands.entity(entity).despawn();
+            }
+        }
     }
 }
 
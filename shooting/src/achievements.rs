@@ -0,0 +1,372 @@
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+use crate::{Console, Menu, MenuAction, MenuMaterials, Options, Scoreboard, Stats};
+
+/// Where the achievements are defined; the built-in list is used when it is missing.
+pub const ACHIEVEMENTS_FILE: &str = "achievements.ron";
+/// Ids of the unlocked achievements, kept between runs.
+pub const UNLOCKED_FILE: &str = "achievements_unlocked.ron";
+
+/// Seconds a toast stays on screen.
+const TOAST_TIME: f32 = 4.0;
+
+/// A number of the current run an achievement can be about.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum StatKind {
+    Score,
+    TimeSurvived,
+    JunkKills,
+    ShipKills,
+    ShotsFired,
+    Hits,
+    Accuracy,
+    DamageTaken,
+    EnergiesCollected,
+    /// Energies that appeared and were not collected.
+    EnergiesMissed,
+    BestCombo,
+}
+
+impl StatKind {
+    fn value(self, stats: &Stats, scoreboard: &Scoreboard) -> f32 {
+        match self {
+            StatKind::Score => scoreboard.score() as f32,
+            // whole seconds, as on the game over screen; frame times don't add up to exactly the duration
+            StatKind::TimeSurvived => stats.time_survived.round(),
+            StatKind::JunkKills => stats.junk_kills as f32,
+            StatKind::ShipKills => stats.ship_kills as f32,
+            StatKind::ShotsFired => stats.shots_fired as f32,
+            StatKind::Hits => stats.hits as f32,
+            StatKind::Accuracy => stats.accuracy(),
+            StatKind::DamageTaken => stats.damage_taken as f32,
+            StatKind::EnergiesCollected => stats.energies_collected as f32,
+            StatKind::EnergiesMissed => stats.energies_spawned.saturating_sub(stats.energies_collected) as f32,
+            StatKind::BestCombo => stats.best_combo as f32,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum Condition {
+    AtLeast(StatKind, f32),
+    AtMost(StatKind, f32),
+    All(Vec<Condition>),
+    /// Only checked once the run is over.
+    AtEnd(Box<Condition>),
+}
+
+impl Condition {
+    fn met(&self, stats: &Stats, scoreboard: &Scoreboard, finished: bool) -> bool {
+        match self {
+            Condition::AtLeast(stat, value) => stat.value(stats, scoreboard) >= *value,
+            Condition::AtMost(stat, value) => stat.value(stats, scoreboard) <= *value,
+            Condition::All(conditions) => conditions.iter().all(|c| c.met(stats, scoreboard, finished)),
+            Condition::AtEnd(condition) => finished && condition.met(stats, scoreboard, finished),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Achievement {
+    pub id: String,
+    pub name: String,
+    pub description: String,
+    pub condition: Condition,
+}
+
+/// Sent when an achievement is unlocked, for the toast.
+pub struct AchievementUnlocked(pub Achievement);
+
+/// A notification in the corner of the screen, removed when its timer runs out.
+pub struct Toast(Timer);
+
+pub struct Achievements {
+    pub list: Vec<Achievement>,
+    pub unlocked: Vec<String>,
+    /// Where `unlocked` is saved, the file it was loaded from.
+    pub unlocked_path: PathBuf,
+}
+
+fn builtin() -> Vec<Achievement> {
+    let achievement = |id: &str, name: &str, description: &str, condition| Achievement {
+        id: id.to_string(), name: name.to_string(), description: description.to_string(), condition,
+    };
+    vec![
+        achievement("first_blood", "First Blood", "Destroy an enemy",
+            Condition::AtLeast(StatKind::Score, 1.0)),
+        achievement("ship_hunter", "Ship Hunter", "Destroy 10 enemy ships in one run",
+            Condition::AtLeast(StatKind::ShipKills, 10.0)),
+        achievement("junk_collector", "Space Cleaner", "Destroy 50 pieces of space junk in one run",
+            Condition::AtLeast(StatKind::JunkKills, 50.0)),
+        achievement("combo", "Chain Reaction", "Reach a combo of 5",
+            Condition::AtLeast(StatKind::BestCombo, 5.0)),
+        achievement("untouchable", "Untouchable", "Survive 180 s without taking damage",
+            Condition::All(vec![
+                Condition::AtLeast(StatKind::TimeSurvived, 180.0),
+                Condition::AtMost(StatKind::DamageTaken, 0.0),
+            ])),
+        achievement("collector", "Collector", "Collect every energy in a run",
+            Condition::AtEnd(Box::new(Condition::All(vec![
+                Condition::AtLeast(StatKind::EnergiesCollected, 1.0),
+                Condition::AtMost(StatKind::EnergiesMissed, 0.0),
+            ])))),
+        achievement("sharpshooter", "Sharpshooter", "Finish a run with 50 shots and 80% accuracy",
+            Condition::AtEnd(Box::new(Condition::All(vec![
+                Condition::AtLeast(StatKind::ShotsFired, 50.0),
+                Condition::AtLeast(StatKind::Accuracy, 80.0),
+            ])))),
+    ]
+}
+
+/// The built-in list, nothing unlocked.
+impl Default for Achievements {
+    fn default() -> Self {
+        Achievements { list: builtin(), unlocked: Vec::new(), unlocked_path: PathBuf::from(UNLOCKED_FILE) }
+    }
+}
+
+impl Achievements {
+    pub fn load(list_path: &Path, unlocked_path: &Path) -> Result<Achievements, String> {
+        let list = match fs::read_to_string(list_path) {
+            Ok(text) => ron::de::from_str(&text).map_err(|err| err.to_string())?,
+            Err(_) => builtin(),
+        };
+        // nothing unlocked yet when the file doesn't exist or can't be read
+        let unlocked = fs::read_to_string(unlocked_path).ok()
+            .and_then(|text| ron::de::from_str(&text).ok())
+            .unwrap_or_default();
+        Ok(Achievements { list, unlocked, unlocked_path: unlocked_path.to_path_buf() })
+    }
+
+    fn save(&self) {
+        let result = ron::ser::to_string(&self.unlocked)
+            .map_err(|err| err.to_string())
+            .and_then(|text| fs::write(&self.unlocked_path, text).map_err(|err| err.to_string()));
+        if let Err(err) = result {
+            error!("can't save achievements to {}: {}", self.unlocked_path.display(), err);
+        }
+    }
+
+    /// Unlocks and returns the achievements met that weren't unlocked yet.
+    fn unlock_met(&mut self, stats: &Stats, scoreboard: &Scoreboard, finished: bool) -> Vec<Achievement> {
+        let newly: Vec<Achievement> = self.list.iter()
+            .filter(|a| !self.unlocked.contains(&a.id) && a.condition.met(stats, scoreboard, finished))
+            .cloned()
+            .collect();
+        if newly.is_empty() {
+            return newly;
+        }
+        for achievement in newly.iter() {
+            info!("achievement unlocked: {}", achievement.name);
+            self.unlocked.push(achievement.id.clone());
+        }
+        self.save();
+        newly
+    }
+
+    /// Headless runs, the autopilot, replays and games changed from the console don't count,
+    /// they aren't the player playing.
+    fn counts(options: &Options, console: &Console) -> bool {
+        !options.headless && !options.bot && options.playback.is_none() && !console.cheated
+    }
+
+    pub fn check(mut achievements: ResMut<Achievements>, stats: Res<Stats>, scoreboard: Res<Scoreboard>,
+        options: Res<Options>, console: Res<Console>, mut events: EventWriter<AchievementUnlocked>)
+    {
+        if Achievements::counts(&options, &console) && stats.is_changed() {
+            for achievement in achievements.unlock_met(&stats, &scoreboard, false) {
+                events.send(AchievementUnlocked(achievement));
+            }
+        }
+    }
+
+    pub fn check_end(mut achievements: ResMut<Achievements>, stats: Res<Stats>, scoreboard: Res<Scoreboard>,
+        options: Res<Options>, console: Res<Console>, mut events: EventWriter<AchievementUnlocked>)
+    {
+        if Achievements::counts(&options, &console) {
+            for achievement in achievements.unlock_met(&stats, &scoreboard, true) {
+                events.send(AchievementUnlocked(achievement));
+            }
+        }
+    }
+
+    pub fn show_toasts(mut commands: Commands, asset_server: Res<AssetServer>,
+        mut materials: ResMut<Assets<ColorMaterial>>, mut events: EventReader<AchievementUnlocked>,
+        toasts: Query<&Toast>)
+    {
+        let shown = toasts.iter().count();
+        for (i, AchievementUnlocked(achievement)) in events.iter().enumerate() {
+            let font = asset_server.load("fonts/FiraSans-Bold.ttf");
+            commands.spawn_bundle(NodeBundle {
+                style: Style {
+                    position_type: PositionType::Absolute,
+                    position: Rect {
+                        top: Val::Px(20.0 + (shown + i) as f32 * 80.0),
+                        right: Val::Px(20.0),
+                        ..Default::default()
+                    },
+                    padding: Rect::all(Val::Px(10.0)),
+                    flex_direction: FlexDirection::ColumnReverse,
+                    ..Default::default()
+                },
+                material: materials.add(Color::rgba(0.1, 0.1, 0.2, 0.85).into()),
+                ..Default::default()
+            })
+            .insert(Toast(Timer::from_seconds(TOAST_TIME, false)))
+            .with_children(|parent| {
+                parent.spawn_bundle(TextBundle {
+                    text: Text::with_section(
+                        format!("Achievement unlocked: {}", achievement.name),
+                        TextStyle { font: font.clone(), font_size: 28.0, color: Color::rgb(1.0, 0.85, 0.3) },
+                        Default::default(),
+                    ),
+                    ..Default::default()
+                });
+                parent.spawn_bundle(TextBundle {
+                    text: Text::with_section(
+                        achievement.description.clone(),
+                        TextStyle { font, font_size: 20.0, color: Color::rgb(0.9, 0.9, 0.9) },
+                        Default::default(),
+                    ),
+                    ..Default::default()
+                });
+            });
+        }
+    }
+
+    /// Uses the real frame time, so toasts also go away on menus and during replays.
+    pub fn update_toasts(mut commands: Commands, time: Res<Time>, mut toasts: Query<(Entity, &mut Toast)>) {
+        for (entity, mut toast) in toasts.iter_mut() {
+            if toast.0.tick(time.delta()).finished() {
+                commands.entity(entity).despawn_recursive();
+            }
+        }
+    }
+
+    /// The gallery: every achievement, the unlocked ones highlighted.
+    pub fn enter_gallery(mut commands: Commands, asset_server: Res<AssetServer>, menu_materials: Res<MenuMaterials>,
+        achievements: Res<Achievements>)
+    {
+        let font: Handle<Font> = asset_server.load("fonts/FiraSans-Bold.ttf");
+        let mut sections = Vec::new();
+        for achievement in achievements.list.iter() {
+            let unlocked = achievements.unlocked.contains(&achievement.id);
+            let (mark, color) = if unlocked {
+                ("[x] ", Color::rgb(1.0, 0.85, 0.3))
+            } else {
+                ("[ ] ", Color::rgb(0.5, 0.5, 0.5))
+            };
+            sections.push(TextSection {
+                value: format!("{}{}", mark, achievement.name),
+                style: TextStyle { font: font.clone(), font_size: 30.0, color },
+            });
+            sections.push(TextSection {
+                value: format!(" - {}\n", achievement.description),
+                style: TextStyle { font: font.clone(), font_size: 22.0, color: Color::rgb(0.8, 0.8, 0.8) },
+            });
+        }
+        let title = format!("Achievements {}/{}", achievements.unlocked.len(), achievements.list.len());
+
+        Menu::spawn(&mut commands, &menu_materials, font.clone(), &[("Back", MenuAction::Back)], |parent| {
+            parent.spawn_bundle(TextBundle {
+                text: Text::with_section(
+                    title,
+                    TextStyle { font: font.clone(), font_size: 60.0, color: Color::rgb(0.9, 0.9, 0.9) },
+                    Default::default(),
+                ),
+                ..Default::default()
+            });
+            parent.spawn_bundle(TextBundle {
+                style: Style { margin: Rect::all(Val::Px(20.0)), ..Default::default() },
+                text: Text { sections, ..Default::default() },
+                ..Default::default()
+            });
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn scoreboard(score: usize) -> Scoreboard {
+        let mut scoreboard = Scoreboard::new(1, 5);
+        scoreboard.players[0].score = score;
+        scoreboard
+    }
+
+    fn ids(achievements: &[Achievement]) -> Vec<&str> {
+        achievements.iter().map(|a| a.id.as_str()).collect()
+    }
+
+    /// Achievements saving to a file of their own under the temp directory, removed when dropped.
+    struct Saved(Achievements);
+
+    impl Saved {
+        fn new(name: &str) -> Saved {
+            let path = std::env::temp_dir().join(format!("shooting-{}-{}", std::process::id(), name));
+            Saved(Achievements { unlocked_path: path, ..Default::default() })
+        }
+    }
+
+    impl Drop for Saved {
+        fn drop(&mut self) {
+            let _ = fs::remove_file(&self.0.unlocked_path);
+        }
+    }
+
+    #[test]
+    fn conditions() {
+        let mut stats = Stats::default();
+        stats.shots_fired = 10;
+        stats.hits = 8;
+        let scoreboard = scoreboard(3);
+
+        assert!(Condition::AtLeast(StatKind::Score, 3.0).met(&stats, &scoreboard, false));
+        assert!(!Condition::AtLeast(StatKind::Score, 4.0).met(&stats, &scoreboard, false));
+        assert!(Condition::AtMost(StatKind::DamageTaken, 0.0).met(&stats, &scoreboard, false));
+        assert!(Condition::AtLeast(StatKind::Accuracy, 80.0).met(&stats, &scoreboard, false));
+
+        let both = Condition::All(vec![
+            Condition::AtLeast(StatKind::Hits, 8.0),
+            Condition::AtMost(StatKind::ShotsFired, 9.0),
+        ]);
+        assert!(!both.met(&stats, &scoreboard, false));
+        assert!(Condition::All(vec![]).met(&stats, &scoreboard, false));
+
+        let at_end = Condition::AtEnd(Box::new(Condition::AtLeast(StatKind::Hits, 1.0)));
+        assert!(!at_end.met(&stats, &scoreboard, false));
+        assert!(at_end.met(&stats, &scoreboard, true));
+    }
+
+    #[test]
+    fn end_conditions_wait_for_the_end() {
+        let mut saved = Saved::new("end_unlocked.ron");
+        let mut stats = Stats::default();
+        stats.energies_spawned = 2;
+        stats.energies_collected = 2;
+        let scoreboard = scoreboard(0);
+
+        assert!(saved.0.unlock_met(&stats, &scoreboard, false).is_empty());
+        assert_eq!(ids(&saved.0.unlock_met(&stats, &scoreboard, true)), vec!["collector"]);
+        // once is enough
+        assert!(saved.0.unlock_met(&stats, &scoreboard, true).is_empty());
+
+        stats.energies_spawned = 3;
+        let mut missed = Saved::new("missed_unlocked.ron");
+        assert!(missed.0.unlock_met(&stats, &scoreboard, true).is_empty());
+    }
+
+    #[test]
+    fn unlocked_are_saved_where_they_were_loaded_from() {
+        let mut saved = Saved::new("saved_unlocked.ron");
+        let missing = saved.0.unlocked_path.with_extension("missing");
+        assert_eq!(ids(&saved.0.unlock_met(&Stats::default(), &scoreboard(1), false)), vec!["first_blood"]);
+
+        let loaded = Achievements::load(&missing, &saved.0.unlocked_path).unwrap();
+        assert_eq!(loaded.unlocked, vec!["first_blood".to_string()]);
+        assert_eq!(loaded.unlocked_path, saved.0.unlocked_path);
+    }
+}
@@ -0,0 +1,121 @@
+use bevy::prelude::*;
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+
+/// Where an enemy points its volleys.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum AimMode {
+    /// Straight ahead, where the ship flies.
+    Fixed,
+    /// At the nearest player, where they are now.
+    Current,
+    /// Where the nearest player will be when the shot gets there, if they keep going.
+    Predictive,
+}
+
+/// How an enemy aims, set per kind of enemy in the config.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Aim {
+    pub mode: AimMode,
+    /// Degrees a volley can be off, either way. 0 is a perfect shot.
+    pub jitter: f32,
+}
+
+impl Default for Aim {
+    fn default() -> Self {
+        Aim { mode: AimMode::Current, jitter: 0.0 }
+    }
+}
+
+/// Time for a shot at `speed` from the origin to meet a target at `offset` moving at `velocity`,
+/// or `None` when it can't catch up.
+pub fn intercept(offset: Vec2, velocity: Vec2, speed: f32) -> Option<f32> {
+    let a = velocity.dot(velocity) - speed * speed;
+    let b = 2.0 * offset.dot(velocity);
+    let c = offset.dot(offset);
+    if a.abs() < 1e-3 {
+        let t = -c / b;
+        return if t > 0.0 { Some(t) } else { None };
+    }
+    let discriminant = b * b - 4.0 * a * c;
+    if discriminant < 0.0 {
+        return None;
+    }
+    let root = discriminant.sqrt();
+    let (t1, t2) = ((-b - root) / (2.0 * a), (-b + root) / (2.0 * a));
+    let t = if t1 > 0.0 && t2 > 0.0 { t1.min(t2) } else { t1.max(t2) };
+    if t > 0.0 { Some(t) } else { None }
+}
+
+impl Aim {
+    pub fn validate(&self) -> Result<(), String> {
+        if self.jitter >= 0.0 && self.jitter <= 180.0 {
+            Ok(())
+        } else {
+            Err("jitter must be 0-180 degrees".to_string())
+        }
+    }
+
+    /// Direction of a volley fired from `pos` by a ship flying along `heading`. `target` is the
+    /// position and velocity of the nearest player, `speed` that of the shots.
+    pub fn direction<R: Rng>(&self, rng: &mut R, pos: Vec2, heading: Vec2, target: Option<(Vec2, Vec2)>,
+        speed: f32) -> Vec2
+    {
+        let aim_at = match (self.mode, target) {
+            (AimMode::Fixed, _) | (_, None) => None,
+            (AimMode::Current, Some((target, _))) => Some(target),
+            (AimMode::Predictive, Some((target, velocity))) => {
+                // aim where the player is when nothing can catch them
+                let lead = intercept(target - pos, velocity, speed).unwrap_or(0.0);
+                Some(target + velocity * lead)
+            }
+        };
+        let direction = aim_at
+            .map(|aim_at| (aim_at - pos).normalize_or_zero())
+            .filter(|direction| *direction != Vec2::ZERO)
+            .unwrap_or(heading);
+        if self.jitter <= 0.0 {
+            return direction;
+        }
+        let off = rng.gen_range(-self.jitter..self.jitter).to_radians();
+        let (sin, cos) = off.sin_cos();
+        Vec2::new(direction.x * cos - direction.y * sin, direction.x * sin + direction.y * cos)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_time(time: Option<f32>, expected: f32) {
+        let time = time.expect("no intercept");
+        assert!((time - expected).abs() < 1e-3, "{} != {}", time, expected);
+    }
+
+    #[test]
+    fn stationary_target() {
+        assert_time(intercept(Vec2::new(300.0, 0.0), Vec2::ZERO, 100.0), 3.0);
+        assert_time(intercept(Vec2::new(-300.0, 400.0), Vec2::ZERO, 250.0), 2.0);
+    }
+
+    #[test]
+    fn head_on_target() {
+        assert_time(intercept(Vec2::new(300.0, 0.0), Vec2::new(-50.0, 0.0), 100.0), 2.0);
+        // as fast as the shot, where the quadratic turns linear
+        assert_time(intercept(Vec2::new(300.0, 0.0), Vec2::new(-100.0, 0.0), 100.0), 1.5);
+    }
+
+    #[test]
+    fn crossing_target() {
+        // 300 across and 80 t along meet 100 t of shot at t = 5
+        assert_time(intercept(Vec2::new(300.0, 0.0), Vec2::new(0.0, 80.0), 100.0), 5.0);
+    }
+
+    #[test]
+    fn target_too_fast() {
+        assert_eq!(intercept(Vec2::new(300.0, 0.0), Vec2::new(150.0, 0.0), 100.0), None);
+        assert_eq!(intercept(Vec2::new(300.0, 0.0), Vec2::new(100.0, 0.0), 100.0), None);
+        assert_eq!(intercept(Vec2::new(300.0, 0.0), Vec2::new(0.0, 150.0), 100.0), None);
+    }
+}
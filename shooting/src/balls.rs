@@ -1,94 +1,105 @@
-use crate::{Params, TIME_STEP, Cannon, Direction, Collider, Particles, Scoreboard};
+use crate::{Params, TIME_STEP, Cannon, Collider, Particles, Scoreboard, GameTime, GameConfig, PlayerInput,
+    GameEvent, Enemies, Enemy, GameRng};
 use bevy::prelude::*;
 use bevy::sprite::collide_aabb::collide;
 
-pub struct BallTimer;
+/// Reload timer of one player's cannon.
+pub struct BallTimer(usize);
+
+/// The player who fired a shot, who scores its kills.
+pub struct Owner(pub usize);
 
 pub struct Balls;
 
 pub struct Ball {
-    speed: Vec2,
+    pub speed: Vec2,
 }
 
 impl Balls {
-    pub fn spawn(commands: &mut Commands, pos: Vec2, direction: Vec2, params: &Res<Params>) {
+    /// Spawns an enemy ball.
+    pub fn spawn(commands: &mut Commands, pos: Vec2, direction: Vec2, speed: f32, params: &Res<Params>) -> Entity {
         let material = params.ball_enemy_color.clone();
         commands.spawn_bundle(SpriteBundle {
-            material: material,
+            material,
             transform: Transform::from_xyz(pos.x, pos.y, 2.0),
-            sprite: Sprite::new(params.ball.clone()),
+            sprite: Sprite::new(params.ball),
             ..Default::default()
         })
-        .insert(Ball { speed: direction * 500.0 })
-        .insert(Collider::Enemyball);
+        .insert(Ball { speed: direction * speed })
+        .insert(Collider::Enemyball)
+        .id()
     }
 
-    pub fn spawner(mut commands: Commands, mouse_input: Res<Input<MouseButton>>, mut materials: ResMut<Assets<ColorMaterial>>, 
-        query: Query<(&Cannon, &Sprite, &Transform)>, params: Res<Params>, 
-        mut query_timer: Query<&mut Timer, With<BallTimer>>, time: Res<Time>, windows: Res<Windows>)
+    pub fn spawner(mut commands: Commands, 
+        query: Query<(&Cannon, &PlayerInput, &Sprite, &Transform)>, params: Res<Params>, config: Res<GameConfig>,
+        mut query_timer: Query<(&mut Timer, &BallTimer)>, time: Res<GameTime>, mut events: EventWriter<GameEvent>)
     {
-        let mut can_shoot = true;
+        for (cannon, input, sprite, transform) in query.iter() {
+            let mut can_shoot = true;
+            let mut timer = query_timer.iter_mut().find(|(_, owner)| owner.0 == cannon.player);
 
-        if let Ok(mut timer) = query_timer.single_mut() {
-            if !timer.tick(time.delta()).finished() {
-                can_shoot = false;
+            if let Some((timer, _)) = &mut timer {
+                if !timer.tick(time.delta()).finished() {
+                    can_shoot = false;
+                }
             }
-        }
 
-        let (cannon, sprite,transform) = query.single().unwrap();
-        let cannon_position = 
-            if cannon.direction == Direction::Right {
-                Vec2::new(transform.translation.x + sprite.size.x * 0.5, transform.translation.y)
-            } else {
-                Vec2::new(transform.translation.x - sprite.size.x * 0.5, transform.translation.y)
-            };
-        
-        if mouse_input.just_pressed(MouseButton::Left) && can_shoot {
-            let win = windows.get_primary().unwrap();
-            let cursor_position = win.cursor_position().unwrap();
-            let size = Vec2::new(win.width() as f32, win.height() as f32);
-            let mouse_position = cursor_position - size * 0.5;
-            let ball_speed = mouse_position.normalize_or_zero() * 500.0;
+            let cannon_position = cannon.muzzle(transform.translation.truncate(), sprite.size);
             
-            commands.spawn_bundle(SpriteBundle {
-                material: params.ball_self_color.clone(),
-                transform: Transform::from_xyz(cannon_position.x, cannon_position.y, 2.0),
-                sprite: Sprite::new(params.ball.clone()),
-                ..Default::default()
-            })
-            .insert(Ball { speed: ball_speed })
-            .insert(Collider::Selfball);
-            
-            if let Ok(mut timer) = query_timer.single_mut() {
-                timer.reset();
-            } else {
-                commands.spawn().insert(Timer::from_seconds(0.5, false))
-                    .insert(BallTimer);
+            if input.fire && can_shoot {
+                let target = match input.aim {
+                    Some(target) => target,
+                    None => continue,
+                };
+                let ball_speed = (target - cannon_position).normalize_or_zero() * config.ball_speed;
+                
+                commands.spawn_bundle(SpriteBundle {
+                    material: params.ball_self_color.clone(),
+                    transform: Transform::from_xyz(cannon_position.x, cannon_position.y, 2.0),
+                    sprite: Sprite::new(params.ball),
+                    ..Default::default()
+                })
+                .insert(Ball { speed: ball_speed })
+                .insert(Owner(cannon.player))
+                .insert(Collider::Selfball);
+                events.send(GameEvent::ShotFired);
+                
+                if let Some((timer, _)) = &mut timer {
+                    timer.reset();
+                } else {
+                    commands.spawn().insert(Timer::from_seconds(config.shot_interval, false))
+                        .insert(BallTimer(cannon.player));
+                }
             }
         }
     }
 
-    pub fn update(mut commands: Commands, mut query_balls: Query<(Entity, &Ball, &mut Transform)>, params: Res<Params>){
-        let xbound = params.background.x / 2.0;
-        let ybound = params.background.y / 2.0;
-        for (entity, ball, mut transform) in query_balls.iter_mut() {
+    pub fn update(mut query_balls: Query<(&Ball, &mut Transform)>) {
+        for (ball, mut transform) in query_balls.iter_mut() {
             transform.translation.x += ball.speed.x * TIME_STEP;
             transform.translation.y += ball.speed.y * TIME_STEP;
         }
     }
 
     pub fn collision(mut commands: Commands, 
-        colliders: Query<(Entity, &Sprite, &Transform, &Collider)>,
-        mut scoreboard: ResMut<Scoreboard>,
-        particles: Res<Particles>)
+        colliders: Query<(Entity, &Sprite, &Transform, &Collider)>, owners: Query<&Owner>, junk: Query<&Enemy>,
+        balls: Query<&Ball>, mut scoreboard: ResMut<Scoreboard>, mut materials: ResMut<Assets<ColorMaterial>>,
+        params: Res<Params>, mut rng: ResMut<GameRng>, particles: Res<Particles>, config: Res<GameConfig>,
+        mut events: EventWriter<GameEvent>)
     {   
+        // two shots hitting the same target in one frame only destroy it once
+        let mut destroyed = Vec::new();
         let selfball = 
             colliders.iter().filter(|(_,_,_,collider)| **collider == Collider::Selfball);
         for (entity, sprite, transform, _) in selfball {
             let enemies = 
             colliders.iter()
                 .filter(|(_,_,_,collider)| **collider == Collider::Enemyship || **collider == Collider::Spacejunk);
-            for (entity2, sprite2, transform2, _) in enemies {
+            let mut hit = false;
+            for (entity2, sprite2, transform2, collider2) in enemies {
+                if hit || destroyed.contains(&entity2) {
+                    continue;
+                }
                 let collision = collide(
                     transform.translation,
                     sprite.size,
@@ -99,10 +110,34 @@ impl Balls {
                     let pos = Vec2::new(transform.translation.x, transform.translation.y);
                     commands.entity(entity).despawn();
                     commands.entity(entity2).despawn();
-                    scoreboard.score += 1;
+                    destroyed.push(entity2);
+                    let mut score = config.score_per_kill;
+                    if let Ok(enemy) = junk.get(entity2) {
+                        score = enemy.score(config.score_per_kill);
+                        // the pieces carry on with the push of the shot
+                        let mut enemy = enemy.clone();
+                        if let Ok(ball) = balls.get(entity) {
+                            enemy.push(ball.speed * config.shot_mass, sprite2.size);
+                        }
+                        let pos2 = transform2.translation.truncate();
+                        let pieces = Enemies::split(&mut commands, &mut materials, &params, &mut rng.0, &config,
+                            pos2, sprite2.size, &enemy);
+                        for _ in 0..pieces {
+                            events.send(GameEvent::Spawned(Collider::Spacejunk));
+                        }
+                    }
+                    let owner = owners.get(entity).map(|owner| owner.0).unwrap_or(0);
+                    if let Some(player) = scoreboard.players.get_mut(owner) {
+                        player.score += score;
+                    }
                     Particles::spawn(&mut commands, pos, (*particles).clone());
+                    events.send(GameEvent::Killed(*collider2));
+                    hit = true;
                 }            
             }
+            if hit {
+                events.send(GameEvent::Hit);
+            }
         } 
     }
 }
\ No newline at end of file
@@ -0,0 +1,220 @@
+use bevy::prelude::*;
+use crate::{AppState, Ball, Cannon, Collider, Enemy, EnemyShip, GameConfig, Options, Params, PlayerInput,
+    Scoreboard, intercept};
+
+/// Seconds ahead the autopilot looks for something about to hit the ship.
+const LOOKAHEAD: f32 = 1.0;
+/// Room it keeps around the ship on top of the hitboxes.
+const MARGIN: f32 = 40.0;
+/// Distance it likes to keep from the enemy it is shooting at.
+const STANDOFF: (f32, f32) = (300.0, 600.0);
+/// How far it shoots.
+const RANGE: f32 = 1200.0;
+/// Distance to the walls under which it turns back toward the middle.
+const WALL: f32 = 250.0;
+
+/// Flies player one with `--bot`, through the same `PlayerInput` as the keyboard and mouse:
+/// it dodges what is about to hit it, goes for energy when low on health and otherwise keeps
+/// a distance from the nearest enemy and shoots where it will be.
+pub struct Autopilot;
+
+/// One collider as the autopilot sees it.
+#[derive(Clone, Copy)]
+struct Seen {
+    collider: Collider,
+    pos: Vec2,
+    /// Units per second.
+    velocity: Vec2,
+    /// Longest side of its sprite.
+    size: f32,
+}
+
+/// The ship it flies.
+struct Ship {
+    pos: Vec2,
+    velocity: Vec2,
+    size: f32,
+    /// Half its health or less: it goes for energy.
+    low: bool,
+}
+
+impl Autopilot {
+    pub fn drive(options: Res<Options>, state: Res<State<AppState>>, params: Res<Params>,
+        config: Res<GameConfig>, scoreboard: Res<Scoreboard>,
+        mut ships: Query<(&Cannon, &Sprite, &Transform, &mut PlayerInput)>,
+        colliders: Query<(&Sprite, &Transform, &Collider, Option<&Ball>, Option<&Enemy>, Option<&EnemyShip>)>)
+    {
+        if !options.bot || *state.current() != AppState::InGame {
+            return;
+        }
+        let (cannon, sprite, transform, mut input) = match ships.iter_mut().find(|(cannon, ..)| cannon.player == 0) {
+            Some(ship) => ship,
+            None => return,
+        };
+        let health = scoreboard.players.get(cannon.player).map_or(0, |player| player.health);
+        let ship = Ship {
+            pos: transform.translation.truncate(),
+            velocity: cannon.velocity,
+            size: sprite.size.max_element(),
+            low: health * 2 <= config.health,
+        };
+        let seen = colliders.iter().map(|(sprite, transform, collider, ball, enemy, enemyship)| Seen {
+            collider: *collider,
+            pos: transform.translation.truncate(),
+            velocity: match (ball, enemy, enemyship) {
+                (Some(ball), _, _) => ball.speed,
+                (_, Some(enemy), _) => enemy.velocity(),
+                (_, _, Some(enemyship)) => enemyship.velocity(),
+                _ => Vec2::ZERO,
+            },
+            size: sprite.size.max_element(),
+        });
+        *input = Autopilot::decide(&ship, seen, params.background * 0.5, config.ball_speed);
+    }
+
+    /// What to do for `ship` among what it sees, on a field reaching `half` from the middle.
+    fn decide(ship: &Ship, seen: impl Iterator<Item = Seen>, half: Vec2, ball_speed: f32) -> PlayerInput {
+        let pos = ship.pos;
+        let reach = ship.size * 0.5 + MARGIN;
+
+        // out of the way of whatever gets closest within the lookahead, the closer the harder
+        let mut dodge = Vec2::ZERO;
+        let mut target: Option<(Vec2, Vec2)> = None;
+        let mut energy: Option<Vec2> = None;
+        for Seen { collider, pos: other, velocity, size } in seen {
+            match collider {
+                Collider::Selfball => continue,
+                Collider::Energy => {
+                    if energy.is_none_or(|energy| pos.distance(other) < pos.distance(energy)) {
+                        energy = Some(other);
+                    }
+                    continue;
+                }
+                Collider::Spacejunk | Collider::Enemyship => {
+                    if target.is_none_or(|(target, _)| pos.distance(other) < pos.distance(target)) {
+                        target = Some((other, velocity));
+                    }
+                }
+                Collider::Enemyball => {}
+            }
+            let offset = other - pos;
+            let relative = velocity - ship.velocity;
+            let time = if relative.length_squared() > 0.0 {
+                (-offset.dot(relative) / relative.length_squared()).clamp(0.0, LOOKAHEAD)
+            } else {
+                0.0
+            };
+            let closest = offset + relative * time;
+            let clearance = reach + size * 0.5;
+            if closest.length() < clearance {
+                let away = if closest == Vec2::ZERO { Vec2::new(-relative.y, relative.x) } else { -closest };
+                dodge += away.normalize_or_zero() * (1.0 - closest.length() / clearance) * (2.0 - time / LOOKAHEAD);
+            }
+        }
+
+        // where it wants to be when nothing is coming
+        let mut goal = match (energy.filter(|_| ship.low), target) {
+            (Some(energy), _) => (energy - pos).normalize_or_zero(),
+            (None, Some((target, _))) => {
+                let distance = pos.distance(target);
+                if distance > STANDOFF.1 {
+                    (target - pos).normalize_or_zero()
+                } else if distance < STANDOFF.0 {
+                    (pos - target).normalize_or_zero()
+                } else {
+                    Vec2::ZERO
+                }
+            }
+            (None, None) => Vec2::ZERO,
+        };
+        if pos.x.abs() > half.x - WALL || pos.y.abs() > half.y - WALL {
+            goal += -pos.normalize_or_zero();
+        }
+
+        let movement = if dodge != Vec2::ZERO { dodge * 2.0 + goal * 0.5 } else { goal };
+        PlayerInput {
+            movement: if movement.length() > 0.1 { movement.normalize() } else { Vec2::ZERO },
+            // lead the shot, like the enemies with predictive aim
+            aim: target.map(|(target, velocity)| {
+                let lead = intercept(target - pos, velocity, ball_speed).unwrap_or(0.0);
+                target + velocity * lead
+            }),
+            fire: target.is_some_and(|(target, _)| pos.distance(target) < RANGE),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Half the size of the field.
+    fn half() -> Vec2 {
+        Vec2::new(2000.0, 2000.0)
+    }
+
+    fn ship(low: bool) -> Ship {
+        Ship { pos: Vec2::ZERO, velocity: Vec2::ZERO, size: 60.0, low }
+    }
+
+    fn seen(collider: Collider, x: f32, y: f32, velocity: Vec2) -> Seen {
+        Seen { collider, pos: Vec2::new(x, y), velocity, size: 40.0 }
+    }
+
+    #[test]
+    fn fires_at_the_nearest_enemy_in_range() {
+        let input = Autopilot::decide(&ship(false), vec![
+            seen(Collider::Enemyship, 900.0, 0.0, Vec2::ZERO),
+            seen(Collider::Spacejunk, 0.0, 400.0, Vec2::ZERO),
+        ].into_iter(), half(), 800.0);
+        assert!(input.fire);
+        assert_eq!(input.aim, Some(Vec2::new(0.0, 400.0)));
+        // within the standoff distance it stays put
+        assert_eq!(input.movement, Vec2::ZERO);
+
+        let input = Autopilot::decide(&ship(false), vec![seen(Collider::Enemyship, RANGE + 1.0, 0.0, Vec2::ZERO)]
+            .into_iter(), half(), 800.0);
+        assert!(!input.fire);
+        // too far: it closes in
+        assert!(input.movement.x > 0.9);
+
+        let input = Autopilot::decide(&ship(false), vec![].into_iter(), half(), 800.0);
+        assert!(!input.fire && input.aim.is_none());
+    }
+
+    #[test]
+    fn leads_a_moving_target() {
+        let input = Autopilot::decide(&ship(false), vec![seen(Collider::Enemyship, 400.0, 0.0, Vec2::new(0.0, 100.0))]
+            .into_iter(), half(), 800.0);
+        assert!(input.aim.unwrap().y > 0.0);
+    }
+
+    #[test]
+    fn gets_out_of_the_way_of_a_shot() {
+        // a ball coming straight down at it, slightly to the right
+        let input = Autopilot::decide(&ship(false), vec![seen(Collider::Enemyball, 10.0, 300.0, Vec2::new(0.0, -500.0))]
+            .into_iter(), half(), 800.0);
+        assert!(input.movement.x < -0.5, "{}", input.movement);
+
+        // one flying away is no danger
+        let input = Autopilot::decide(&ship(false), vec![seen(Collider::Enemyball, 10.0, 300.0, Vec2::new(0.0, 500.0))]
+            .into_iter(), half(), 800.0);
+        assert_eq!(input.movement, Vec2::ZERO);
+    }
+
+    #[test]
+    fn goes_for_energy_when_low() {
+        let things = vec![
+            seen(Collider::Enemyship, 400.0, 0.0, Vec2::ZERO),
+            seen(Collider::Energy, -500.0, 0.0, Vec2::ZERO),
+        ];
+        assert!(Autopilot::decide(&ship(true), things.clone().into_iter(), half(), 800.0).movement.x < -0.9);
+        assert_eq!(Autopilot::decide(&ship(false), things.into_iter(), half(), 800.0).movement, Vec2::ZERO);
+    }
+
+    #[test]
+    fn turns_back_from_the_walls() {
+        let ship = Ship { pos: Vec2::new(half().x - 10.0, 0.0), ..ship(false) };
+        assert!(Autopilot::decide(&ship, vec![].into_iter(), half(), 800.0).movement.x < -0.9);
+    }
+}
@@ -0,0 +1,60 @@
+use bevy::prelude::*;
+use crate::{Cannon, GameEvent, GameTime, MainCamera, Params, SessionEntity, Settings};
+
+/// Furthest the camera zooms out to keep every ship in view.
+const MAX_ZOOM: f32 = 2.5;
+/// Room kept between the ships and the edge of the view, in world units.
+const MARGIN: f32 = 150.0;
+
+/// The camera shared by the players: it follows the ships and frames all of them.
+pub struct GameCamera;
+
+impl GameCamera {
+    pub fn setup(mut commands: Commands) {
+        commands.spawn_bundle(OrthographicCameraBundle::new_2d())
+            .insert(MainCamera)
+            .insert(SessionEntity);
+    }
+
+    /// Centers the view between the ships and zooms out when they are too far apart to fit in it.
+    pub fn follow(params: Res<Params>, ships: Query<&Transform, (With<Cannon>, Without<MainCamera>)>,
+        mut cameras: Query<&mut Transform, With<MainCamera>>)
+    {
+        let mut positions = ships.iter().map(|transform| transform.translation.truncate());
+        let first = match positions.next() {
+            Some(first) => first,
+            None => return,
+        };
+        let (min, max) = positions.fold((first, first), |(min, max), pos| (min.min(pos), max.max(pos)));
+        let center = (min + max) * 0.5;
+        let spread = max - min + Vec2::splat(MARGIN * 2.0);
+        let zoom = (spread / params.bounds).max_element().clamp(1.0, MAX_ZOOM);
+        for mut transform in cameras.iter_mut() {
+            transform.translation.x = center.x;
+            transform.translation.y = center.y;
+            // scaling the camera rather than the projection keeps the letterbox bars in place
+            transform.scale = Vec3::new(zoom, zoom, 1.0);
+        }
+    }
+
+    /// Shakes the camera for a moment after taking damage, unless turned off on the options screen.
+    pub fn shake(settings: Res<Settings>, time: Res<GameTime>, mut events: EventReader<GameEvent>,
+        mut left: Local<f32>, mut cameras: Query<&mut Transform, With<MainCamera>>)
+    {
+        const DURATION: f32 = 0.3;
+        const STRENGTH: f32 = 12.0;
+        if events.iter().any(|event| matches!(event, GameEvent::DamageTaken)) {
+            *left = DURATION;
+        }
+        *left = (*left - time.delta().as_secs_f32()).max(0.0);
+        if !settings.screen_shake || *left <= 0.0 {
+            return;
+        }
+        let t = *left * 60.0;
+        let offset = Vec2::new((t * 1.3).sin(), (t * 1.7).cos()) * STRENGTH * *left / DURATION;
+        for mut transform in cameras.iter_mut() {
+            transform.translation.x += offset.x;
+            transform.translation.y += offset.y;
+        }
+    }
+}
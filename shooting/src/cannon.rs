@@ -1,114 +1,346 @@
-use crate::{Params, TIME_STEP, AppState, Direction, Scoreboard, Particles, Collider};
+use crate::{Params, TIME_STEP, AppState, Scoreboard, Particles, Collider, GameConfig, PlayerInput,
+    GameTime, GameEvent, SessionEntity, PlayerCount, Console, Options, Difficulty, Flight, FlightModel, Balls};
 use bevy::prelude::*;
 use bevy::sprite::collide_aabb::collide;
+use std::f32::consts::PI;
+
 pub struct Cannon {
+    /// Index of the player in the `Scoreboard`.
+    pub player: usize,
     pub speed: f32,
-    pub direction: Direction,
+    /// Seconds left during which hits are ignored.
+    pub invulnerable: f32,
+    pub knockback: Vec2,
+    /// Units per second it moved last frame, for enemies leading their shots.
+    pub velocity: Vec2,
+    pub flight: FlightModel,
+    /// Radians the nose points at, counterclockwise from the right.
+    pub heading: f32,
+    /// Units per second it coasts at in inertia flight, without the knockback.
+    pub drift: Vec2,
+    /// Junk it touched last frame; junk only hurts when it hits, not while it bounces off.
+    pub touching: Vec<Entity>,
 }
 
 impl Cannon {
+    /// Ship colors, so the players can tell who is who.
+    pub const TINTS: [Color; 2] = [Color::rgb(1.0, 1.0, 1.0), Color::rgb(0.6, 0.85, 1.0)];
+
+    /// Share of the ship sprite that can be hit.
+    pub const HITBOX: f32 = 0.7;
+
+    /// Share of a sprite that hits the ship; junk and ships are forgiving, their images have empty corners.
+    pub fn hitbox(collider: Collider) -> f32 {
+        if collider == Collider::Enemyball || collider == Collider::Energy {
+            1.0
+        } else {
+            0.7
+        }
+    }
+
+    /// A ship drawn for a remote game, not driven by this instance.
+    pub fn remote(player: usize) -> Self {
+        Cannon {
+            player, speed: 0.0, invulnerable: 0.0,
+            knockback: Vec2::ZERO, velocity: Vec2::ZERO, flight: FlightModel::Arcade, heading: 0.0,
+            drift: Vec2::ZERO, touching: Vec::new(),
+        }
+    }
+
+    /// Where the ship of `player` out of `players` starts a game: side by side around the center.
+    pub fn start(player: usize, players: usize, size: Vec2) -> Vec2 {
+        Vec2::new((player as f32 - (players - 1) as f32 * 0.5) * size.x * 2.0, 0.0)
+    }
+
+    /// Unit vector along the nose.
+    pub fn nose(&self) -> Vec2 {
+        Vec2::new(self.heading.cos(), self.heading.sin())
+    }
+
+    /// Where shots leave a ship of `size` at `pos`: the tip of the nose.
+    pub fn muzzle(&self, pos: Vec2, size: Vec2) -> Vec2 {
+        pos + self.nose() * size.x * 0.5
+    }
+
+    /// Turns the nose toward `direction`, the short way round, by at most `rate` degrees a second.
+    fn turn(&mut self, direction: Vec2, rate: f32) {
+        if direction == Vec2::ZERO {
+            return;
+        }
+        let wanted = direction.y.atan2(direction.x);
+        let turn = (wanted - self.heading + PI).rem_euclid(PI * 2.0) - PI;
+        let most = rate.to_radians() * TIME_STEP;
+        self.heading = (self.heading + turn.max(-most).min(most)).rem_euclid(PI * 2.0);
+    }
+
+    /// Inertia flight: the nose turns toward the stick and the ship thrusts along it, drifting when let go.
+    /// Returns how far it moves this frame.
+    fn fly(&mut self, direction: Vec2, flight: &Flight) -> Vec2 {
+        if direction != Vec2::ZERO {
+            self.turn(direction, flight.turn_rate);
+            let nose = self.nose();
+            self.drift += nose * flight.thrust * direction.length().min(1.0) * TIME_STEP;
+        }
+        self.drift *= 1.0 - (flight.drag * TIME_STEP).min(1.0);
+        if self.drift.length() > flight.max_speed {
+            self.drift = self.drift.normalize() * flight.max_speed;
+        }
+        self.drift * TIME_STEP
+    }
+
     pub fn setup(mut commands: Commands, mut materials: ResMut<Assets<ColorMaterial>>, params: Res<Params>,
-            asset_server: Res<AssetServer>)
+            asset_server: Res<AssetServer>, config: Res<GameConfig>, players: Res<PlayerCount>,
+            options: Res<Options>, difficulty: Res<Difficulty>)
     {
-        let wall_thickness = params.wall;
-        let size = params.cannon.clone();
-        let bounds = &params.bounds;
+        let size = params.cannon;
         let texture_handle = asset_server.load("images/player-rocket.png");
-        commands.spawn_bundle(SpriteBundle {
-            material: materials.add(texture_handle.into()),
-            sprite: Sprite::new(size),
-            ..Default::default()
-        })
-        .insert(Cannon { speed: 500.0, direction: Direction::Right })
-        .with_children(|parent| {
-            parent.spawn_bundle(OrthographicCameraBundle::new_2d());
-        });
-    } 
-
-    pub fn collision(mut commands: Commands, mut colliders: Query<(Entity, &Sprite, &Transform, &Collider)>,
-        mut self_query: Query<(&Sprite, &Transform), With<Cannon>>, mut state: ResMut<State<AppState>>,
-        mut scoreboard: ResMut<Scoreboard>, particles: Res<Particles>)
+        for player in 0..players.0 {
+            let start = Cannon::start(player, players.0, size);
+            let flight = options.flight.get(player).or(options.flight.last()).copied()
+                .unwrap_or_else(|| config.flight.model(*difficulty));
+            commands.spawn_bundle(SpriteBundle {
+                material: materials.add(ColorMaterial::modulated_texture(texture_handle.clone(),
+                    Cannon::TINTS[player % Cannon::TINTS.len()])),
+                transform: Transform::from_xyz(start.x, start.y, 0.0),
+                sprite: Sprite::new(size),
+                ..Default::default()
+            })
+            .insert(Cannon {
+                player,
+                speed: config.cannon_speed,
+                invulnerable: 0.0,
+                knockback: Vec2::ZERO,
+                velocity: Vec2::ZERO,
+                flight,
+                heading: 0.0,
+                drift: Vec2::ZERO,
+                touching: Vec::new(),
+            })
+            .insert(PlayerInput::default())
+            .insert(SessionEntity);
+        }
+    }
+
+    pub fn collision(mut commands: Commands, colliders: Query<(Entity, &Sprite, &Transform, &Collider)>,
+        mut self_query: Query<(&mut Cannon, &Sprite, &Transform)>, mut scoreboard: ResMut<Scoreboard>,
+        particles: Res<Particles>, config: Res<GameConfig>, mut events: EventWriter<GameEvent>, console: Res<Console>)
     {
-        let (self_sprite, self_transform) = self_query.single_mut().unwrap();
-    
-        let colliders = colliders.iter()
-            .filter(|(_,_,_,collider)| **collider != Collider::Selfball);
-        
-        for (entity, sprite, transform, collider) in colliders {
-            let coeff = if * collider == Collider::Enemyball || *collider == Collider::Energy {
-                1.0
-            } else {
-                0.7
+        // a pickup or a shot touching both ships only counts for the first one
+        let mut consumed = Vec::new();
+
+        for (mut cannon, self_sprite, self_transform) in self_query.iter_mut() {
+            let colliders = colliders.iter()
+                .filter(|(_,_,_,collider)| **collider != Collider::Selfball);
+
+            let mut touching = Vec::new();
+            for (entity, sprite, transform, collider) in colliders {
+                if consumed.contains(&entity) {
+                    continue;
+                }
+                let collision = collide(
+                    self_transform.translation,
+                    self_sprite.size * Cannon::HITBOX,
+                    transform.translation,
+                    sprite.size * Cannon::hitbox(*collider)
+                );
+                if collision.is_some() && *collider == Collider::Spacejunk {
+                    touching.push(entity);
+                    if cannon.touching.contains(&entity) {
+                        continue;
+                    }
+                }
+                if (cannon.invulnerable > 0.0 || console.god) && *collider != Collider::Energy {
+                    continue;
+                }
+                if collision.is_some() {
+                    let pos = Vec2::new(self_transform.translation.x,self_transform.translation.y);
+                    let player = &mut scoreboard.players[cannon.player];
+                    if *collider == Collider::Energy {
+                        player.health += 1;
+                        events.send(GameEvent::EnergyCollected);
+                    } else {
+                        Particles::spawn(&mut commands, pos, (*particles).clone());
+                        if player.health > 0 {
+                            player.health -= 1;
+                        }
+                        events.send(GameEvent::DamageTaken);
+                        cannon.invulnerable = config.invulnerability;
+                        let away = self_transform.translation - transform.translation;
+                        cannon.knockback = Vec2::new(away.x, away.y).normalize_or_zero() * config.knockback;
+                    }
+                    // junk is too big to go; it bounces off, see `Enemies::physics`
+                    if *collider != Collider::Spacejunk {
+                        commands.entity(entity).despawn();
+                        consumed.push(entity);
+                    }
+                }
+            }
+            cannon.touching = touching;
+        }
+    }
+
+    /// A player who is out of health loses their ship; in co-op the other one carries on.
+    pub fn update(mut commands: Commands,
+            mut query: Query<(Entity, &mut Cannon, &mut Sprite, &mut Transform, &PlayerInput)>,
+            params: Res<Params>, scoreboard: Res<Scoreboard>, time: Res<GameTime>, config: Res<GameConfig>) {
+
+        for (entity, mut cannon, mut sprite, mut transform, input) in query.iter_mut() {
+            if scoreboard.players[cannon.player].health == 0 {
+                commands.entity(entity).despawn_recursive();
+                continue;
+            }
+            let direction = input.movement;
+            let start = transform.translation;
+
+            let step = match cannon.flight {
+                FlightModel::Arcade => {
+                    // face where the player aims, else where they go
+                    let pos = transform.translation.truncate();
+                    let facing = input.aim.map(|aim| aim - pos).filter(|facing| *facing != Vec2::ZERO)
+                        .unwrap_or(direction);
+                    cannon.turn(facing, config.cannon_turn_rate);
+                    direction * cannon.speed * TIME_STEP
+                }
+                FlightModel::Inertia => cannon.fly(direction, &config.flight),
             };
-            let collision = collide(
-                self_transform.translation,
-                self_sprite.size * 0.7,
-                transform.translation,
-                sprite.size * coeff
-            );
-            if collision.is_some() {
-                let pos = Vec2::new(self_transform.translation.x,self_transform.translation.y); 
-                if *collider == Collider::Energy {
-                    scoreboard.health += 1;
-                } else {
-                    Particles::spawn(&mut commands, pos, (*particles).clone());
-                    if scoreboard.health > 0 {
-                        scoreboard.health -= 1;
-                    }          
+            // turned, not flipped, but kept upright when facing left
+            transform.rotation = Quat::from_rotation_z(cannon.heading);
+            sprite.flip_y = cannon.heading.cos() < 0.0;
+
+            let translation = &mut transform.translation;
+            translation.x += step.x;
+            translation.y += step.y;
+            translation.x += cannon.knockback.x * TIME_STEP;
+            translation.y += cannon.knockback.y * TIME_STEP;
+            cannon.knockback *= 0.9;
+            cannon.invulnerable = (cannon.invulnerable - time.delta().as_secs_f32()).max(0.0);
+
+            let xmax = params.background.x / 2.0 - params.cannon.x / 2.0;
+            let ymax = params.background.y / 2.0 - params.cannon.y / 2.0;
+            let free = *translation;
+            translation.x = translation.x.min(xmax).max(-xmax);
+            translation.y = translation.y.min(ymax).max(-ymax);
+            // a wall stops the drift into it
+            if translation.x != free.x {
+                cannon.drift.x = 0.0;
+            }
+            if translation.y != free.y {
+                cannon.drift.y = 0.0;
+            }
+            cannon.velocity = (*translation - start).truncate() / TIME_STEP;
+        }
+    }
+
+    /// Blinks the ship while it is invulnerable, tinted red right after the hit.
+    pub fn feedback(mut query: Query<(&Cannon, &Handle<ColorMaterial>, &mut Visible)>,
+        mut materials: ResMut<Assets<ColorMaterial>>, config: Res<GameConfig>)
+    {
+        for (cannon, material, mut visible) in query.iter_mut() {
+            visible.is_visible = cannon.invulnerable <= 0.0 || (cannon.invulnerable * 5.0).fract() < 0.5;
+            if let Some(material) = materials.get_mut(material) {
+                let red = if config.invulnerability > 0.0 { cannon.invulnerable / config.invulnerability } else { 0.0 };
+                let tint = Cannon::TINTS[cannon.player % Cannon::TINTS.len()];
+                let color = Color::rgb(tint.r(), tint.g() * (1.0 - red), tint.b() * (1.0 - red));
+                if material.color != color {
+                    material.color = color;
                 }
-                commands.entity(entity).despawn();
             }
         }
     }
-    
-    pub fn update(keyboard_input: Res<Input<KeyCode>>, mut query: Query<(&mut Cannon, &mut Sprite, &mut Transform)>, 
-            params: Res<Params>, windows: Res<Windows>, scoreboard: Res<Scoreboard>,
-            mut state: ResMut<State<AppState>>) {
+}
 
-        if scoreboard.health == 0 {
-            state.set(AppState::Finish).unwrap();
-            return;
-        }        
-        let (mut cannon, mut sprite, mut transform) = query.single_mut().unwrap();
-        let mut direction = Vec2::new(0.0, 0.0);
-        
-        if keyboard_input.pressed(KeyCode::Up){
-            direction.y += 1.0;
-        }      
-        
-        if keyboard_input.pressed(KeyCode::Down){
-            direction.y -= 1.0;
-        } 
-
-        if keyboard_input.pressed(KeyCode::Left){
-            direction.x -= 1.0;
-            sprite.flip_x = true;
-            cannon.direction = Direction::Left;
-        } 
-
-        if keyboard_input.pressed(KeyCode::Right){
-            direction.x += 1.0;
-            sprite.flip_x = false;
-            cannon.direction = Direction::Right;
-        } 
-
-        let translation = &mut transform.translation;
-        translation.x += direction.x * cannon.speed * TIME_STEP;
-        translation.y += direction.y * cannon.speed * TIME_STEP;
-        
-        let xmax = params.background.x / 2.0 - params.cannon.x / 2.0;
-        let ymax = params.background.y / 2.0 - params.cannon.y / 2.0;
-        translation.x = translation.x.min(xmax).max(-xmax);
-        translation.y = translation.y.min(ymax).max(-ymax);
-    }
-
-    pub fn reset(mut query: Query<&mut Transform, With<Cannon>>, params: Res<Params>){
-        let mut transform = query.single_mut().unwrap();
-        let wall_thickness = params.wall;
-        let size = params.cannon.clone();
-        let bounds = &params.bounds;
-        let translation = &mut transform.translation;
-        translation.x = 0.0;
-        translation.y = 0.0;
-    }
-}
\ No newline at end of file
+/// The players' ships and their shots.
+pub struct CannonPlugin;
+
+impl Plugin for CannonPlugin {
+    fn build(&self, app: &mut AppBuilder) {
+        app
+            .add_system_set(SystemSet::on_enter(AppState::InGame)
+                .with_system(Cannon::setup.system())
+            )
+            .add_system_set(SystemSet::on_update(AppState::InGame)
+                .with_system(Balls::spawner.system().label("events"))
+                .with_system(Cannon::update.system().label("cannon"))
+                .with_system(Cannon::collision.system().label("events"))
+                .with_system(Cannon::feedback.system())
+                .with_system(Balls::update.system())
+                .with_system(Balls::collision.system().label("events"))
+            );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_close(a: f32, b: f32) {
+        assert!((a - b).abs() < 1e-4, "{} != {}", a, b);
+    }
+
+    fn inertia() -> Cannon {
+        Cannon { flight: FlightModel::Inertia, ..Cannon::remote(0) }
+    }
+
+    #[test]
+    fn inertia_builds_up_to_the_top_speed() {
+        let flight = Flight::default();
+        let mut cannon = inertia();
+        let first = cannon.fly(Vec2::X, &flight);
+        assert!(first.x > 0.0);
+        assert!(cannon.fly(Vec2::X, &flight).x > first.x);
+        for _ in 0..1000 {
+            cannon.fly(Vec2::X, &flight);
+        }
+        assert!(cannon.drift.length() <= flight.max_speed + 1e-3);
+        assert!(cannon.drift.length() > flight.max_speed * 0.5);
+    }
+
+    #[test]
+    fn inertia_drifts_on_and_slows_down_when_let_go() {
+        let flight = Flight::default();
+        let mut cannon = inertia();
+        for _ in 0..30 {
+            cannon.fly(Vec2::Y, &flight);
+        }
+        let speed = cannon.drift.length();
+        let step = cannon.fly(Vec2::ZERO, &flight);
+        assert!(step.length() > 0.0);
+        assert!(cannon.drift.length() < speed);
+
+        let no_drag = Flight { drag: 0.0, ..Flight::default() };
+        let drift = cannon.drift;
+        cannon.fly(Vec2::ZERO, &no_drag);
+        assert_eq!(cannon.drift, drift);
+    }
+
+    #[test]
+    fn turn_is_limited_by_the_rate() {
+        let mut cannon = Cannon::remote(0);
+        // a quarter turn a second, one frame
+        cannon.turn(Vec2::Y, 90.0);
+        assert_close(cannon.heading, (90.0 * TIME_STEP).to_radians());
+        // fast enough to get there in one frame, and no further
+        cannon.turn(Vec2::Y, 1e6);
+        assert_close(cannon.heading, PI * 0.5);
+        // no direction, no turn
+        cannon.turn(Vec2::ZERO, 1e6);
+        assert_close(cannon.heading, PI * 0.5);
+    }
+
+    #[test]
+    fn turn_takes_the_short_way_round() {
+        let mut cannon = Cannon::remote(0);
+        cannon.heading = 10f32.to_radians();
+        // 20 degrees clockwise, across 0, rather than 340 the other way
+        cannon.turn(Vec2::new(10f32.to_radians().cos(), -10f32.to_radians().sin()), 1e6);
+        assert_close(cannon.heading, 350f32.to_radians());
+
+        cannon.heading = 350f32.to_radians();
+        cannon.turn(Vec2::new(1.0, -1.0), 10.0 / TIME_STEP);
+        assert_close(cannon.heading, 340f32.to_radians());
+
+        // and back across 0, kept between 0 and a full turn
+        cannon.turn(Vec2::new(20f32.to_radians().cos(), 20f32.to_radians().sin()), 1e6);
+        assert_close(cannon.heading, 20f32.to_radians());
+    }
+}
@@ -0,0 +1,324 @@
+use std::fmt;
+use std::path::PathBuf;
+use crate::{Difficulty, FlightModel, TELEMETRY_DIR};
+
+pub const USAGE: &str = "\
+Space Shooter
+
+USAGE:
+    shooting [OPTIONS]
+
+OPTIONS:
+        --width <PX>           window width (default from the options screen, 1280)
+        --height <PX>          window height (default from the options screen, 720)
+        --virtual <WxH>        fixed virtual resolution, letterboxed (e.g. 1280x720)
+    -f, --fullscreen           start in borderless fullscreen
+        --coop                 two players on one machine, also for Play in the menu
+        --host <PORT>          host a networked co-op game; starts when a player joins
+        --join <ADDR>          join a networked game at host:port
+    -s, --seed <N>             seed for the random generator
+    -d, --difficulty <LEVEL>   easy, normal or hard (default from the options screen, normal)
+    -c, --config <FILE>        gameplay config file (.ron)
+        --flight <MODEL>       arcade or inertia, or one per player: arcade,inertia
+                               (default from the config file, by difficulty)
+        --headless             run without a window; implies --stage 1
+        --bot                  the autopilot flies player one
+        --record <FILE>        record the inputs of the first game
+        --playback <FILE>      replay recorded inputs; implies --stage 1
+        --stage <N>            skip the menu and start at stage N (1-3)
+        --telemetry <DIR>      where session logs are written (default telemetry)
+        --no-telemetry         don't write session logs
+        --help                 print this help";
+
+#[derive(Clone)]
+pub struct Options {
+    pub width: f32,
+    pub height: f32,
+    pub virtual_size: Option<(f32, f32)>,
+    pub fullscreen: bool,
+    pub coop: bool,
+    pub host: Option<u16>,
+    pub join: Option<String>,
+    pub seed: Option<u64>,
+    pub difficulty: Difficulty,
+    pub config: Option<PathBuf>,
+    /// Flight model per player, the last one for the players after it; empty to go by difficulty.
+    pub flight: Vec<FlightModel>,
+    pub headless: bool,
+    /// Player one is flown by the autopilot.
+    pub bot: bool,
+    pub record: Option<PathBuf>,
+    pub playback: Option<PathBuf>,
+    pub stage: Option<u32>,
+    /// Directory for the session logs, `None` when turned off.
+    pub telemetry: Option<PathBuf>,
+    pub help: bool,
+}
+
+impl Default for Options {
+    fn default() -> Self {
+        Options {
+            width: 1280.0,
+            height: 720.0,
+            virtual_size: None,
+            fullscreen: false,
+            coop: false,
+            host: None,
+            join: None,
+            seed: None,
+            difficulty: Difficulty::Normal,
+            config: None,
+            flight: Vec::new(),
+            headless: false,
+            bot: false,
+            record: None,
+            playback: None,
+            stage: None,
+            telemetry: Some(PathBuf::from(TELEMETRY_DIR)),
+            help: false,
+        }
+    }
+}
+
+#[derive(Debug, PartialEq)]
+pub enum CliError {
+    UnknownOption(String),
+    MissingValue(String),
+    InvalidValue { option: String, value: String },
+    Conflict(&'static str, &'static str),
+}
+
+impl fmt::Display for CliError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            CliError::UnknownOption(option) => write!(f, "unknown option '{}'", option),
+            CliError::MissingValue(option) => write!(f, "option '{}' needs a value", option),
+            CliError::InvalidValue { option, value } =>
+                write!(f, "invalid value '{}' for option '{}'", value, option),
+            CliError::Conflict(a, b) => write!(f, "options '{}' and '{}' can't be used together", a, b),
+        }
+    }
+}
+
+impl Options {
+    pub const STAGES: u32 = 3;
+
+    /// Parses the command line, without the program name.
+    pub fn parse<I: IntoIterator<Item = String>>(args: I) -> Result<Options, CliError> {
+        Options::parse_with(Options::default(), args)
+    }
+
+    /// Like `parse`, but options missing from the command line keep the values of `defaults`.
+    pub fn parse_with<I: IntoIterator<Item = String>>(defaults: Options, args: I) -> Result<Options, CliError> {
+        let mut options = defaults;
+        let mut args = args.into_iter();
+
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "--help" => options.help = true,
+                "-f" | "--fullscreen" => options.fullscreen = true,
+                "--headless" => options.headless = true,
+                "--bot" => options.bot = true,
+                "--coop" => options.coop = true,
+                "--width" => options.width = parse_size(&arg, value(&arg, &mut args)?)?,
+                "--height" => options.height = parse_size(&arg, value(&arg, &mut args)?)?,
+                "--virtual" => {
+                    let v = value(&arg, &mut args)?;
+                    let invalid = || CliError::InvalidValue { option: arg.clone(), value: v.clone() };
+                    let mut parts = v.splitn(2, 'x');
+                    let w = parts.next().ok_or_else(invalid)?;
+                    let h = parts.next().ok_or_else(invalid)?;
+                    options.virtual_size = Some((parse_size(&arg, w.to_string())?, parse_size(&arg, h.to_string())?));
+                }
+                "-s" | "--seed" => {
+                    let v = value(&arg, &mut args)?;
+                    options.seed = Some(v.parse().map_err(|_| CliError::InvalidValue { option: arg.clone(), value: v })?);
+                }
+                "-d" | "--difficulty" => {
+                    let v = value(&arg, &mut args)?;
+                    options.difficulty = v.parse().map_err(|_| CliError::InvalidValue { option: arg.clone(), value: v })?;
+                }
+                "--host" => {
+                    let v = value(&arg, &mut args)?;
+                    options.host = Some(v.parse().map_err(|_| CliError::InvalidValue { option: arg.clone(), value: v })?);
+                }
+                "--join" => options.join = Some(value(&arg, &mut args)?),
+                "-c" | "--config" => options.config = Some(PathBuf::from(value(&arg, &mut args)?)),
+                "--flight" => {
+                    let v = value(&arg, &mut args)?;
+                    options.flight = v.split(',').map(|model| model.parse()).collect::<Result<_, _>>()
+                        .map_err(|_| CliError::InvalidValue { option: arg.clone(), value: v })?;
+                }
+                "--record" => options.record = Some(PathBuf::from(value(&arg, &mut args)?)),
+                "--playback" => options.playback = Some(PathBuf::from(value(&arg, &mut args)?)),
+                "--telemetry" => options.telemetry = Some(PathBuf::from(value(&arg, &mut args)?)),
+                "--no-telemetry" => options.telemetry = None,
+                "--stage" => {
+                    let v = value(&arg, &mut args)?;
+                    match v.parse::<u32>() {
+                        Ok(stage) if (1..=Options::STAGES).contains(&stage) => options.stage = Some(stage),
+                        _ => return Err(CliError::InvalidValue { option: arg.clone(), value: v }),
+                    }
+                }
+                _ => return Err(CliError::UnknownOption(arg)),
+            }
+        }
+
+        if options.record.is_some() && options.playback.is_some() {
+            return Err(CliError::Conflict("--record", "--playback"));
+        }
+        if options.host.is_some() && options.join.is_some() {
+            return Err(CliError::Conflict("--host", "--join"));
+        }
+        let networked = match (&options.host, &options.join) {
+            (Some(_), _) => Some("--host"),
+            (_, Some(_)) => Some("--join"),
+            _ => None,
+        };
+        if let Some(network) = networked {
+            if options.record.is_some() {
+                return Err(CliError::Conflict(network, "--record"));
+            }
+            if options.playback.is_some() {
+                return Err(CliError::Conflict(network, "--playback"));
+            }
+        }
+        if options.host.is_some() {
+            options.coop = true;
+            if options.stage.is_none() {
+                options.stage = Some(1);
+            }
+        }
+        if options.bot && options.playback.is_some() {
+            return Err(CliError::Conflict("--bot", "--playback"));
+        }
+        if options.coop && options.record.is_some() {
+            return Err(CliError::Conflict("--coop", "--record"));
+        }
+        if options.coop && options.playback.is_some() {
+            return Err(CliError::Conflict("--coop", "--playback"));
+        }
+        if options.headless && options.fullscreen {
+            return Err(CliError::Conflict("--headless", "--fullscreen"));
+        }
+        if (options.headless || options.playback.is_some()) && options.stage.is_none() {
+            options.stage = Some(1);
+        }
+        Ok(options)
+    }
+}
+
+fn value<I: Iterator<Item = String>>(option: &str, args: &mut I) -> Result<String, CliError> {
+    args.next().ok_or_else(|| CliError::MissingValue(option.to_string()))
+}
+
+fn parse_size(option: &str, value: String) -> Result<f32, CliError> {
+    match value.parse::<f32>() {
+        Ok(size) if size >= 1.0 && size.is_finite() => Ok(size),
+        _ => Err(CliError::InvalidValue { option: option.to_string(), value }),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(args: &[&str]) -> Result<Options, CliError> {
+        Options::parse(args.iter().map(|arg| arg.to_string()))
+    }
+
+    #[test]
+    fn defaults_without_arguments() {
+        let options = parse(&[]).unwrap();
+        assert_eq!(options.width, 1280.0);
+        assert_eq!(options.difficulty, Difficulty::Normal);
+        assert_eq!(options.stage, None);
+        assert_eq!(options.telemetry, Some(PathBuf::from(TELEMETRY_DIR)));
+        assert!(!options.headless && !options.bot && !options.help);
+    }
+
+    #[test]
+    fn valid_flags() {
+        let options = parse(&["--width", "800", "--height", "600", "--virtual", "640x360", "-s", "42",
+            "-d", "hard", "-c", "game.ron", "--flight", "arcade,inertia", "--headless", "--bot",
+            "--record", "game-1.ron", "--stage", "2"]).unwrap();
+        assert_eq!((options.width, options.height), (800.0, 600.0));
+        assert_eq!(options.virtual_size, Some((640.0, 360.0)));
+        assert_eq!(options.seed, Some(42));
+        assert_eq!(options.difficulty, Difficulty::Hard);
+        assert_eq!(options.config, Some(PathBuf::from("game.ron")));
+        assert_eq!(options.flight, vec![FlightModel::Arcade, FlightModel::Inertia]);
+        assert!(options.headless && options.bot);
+        assert_eq!(options.record, Some(PathBuf::from("game-1.ron")));
+        assert_eq!(options.stage, Some(2));
+        assert_eq!(parse(&["--no-telemetry"]).unwrap().telemetry, None);
+        assert_eq!(parse(&["--telemetry", "logs"]).unwrap().telemetry, Some(PathBuf::from("logs")));
+    }
+
+    #[test]
+    fn implied_options() {
+        assert_eq!(parse(&["--headless"]).unwrap().stage, Some(1));
+        assert_eq!(parse(&["--playback", "game.ron"]).unwrap().stage, Some(1));
+        assert_eq!(parse(&["--headless", "--stage", "3"]).unwrap().stage, Some(3));
+        let host = parse(&["--host", "4000"]).unwrap();
+        assert_eq!(host.host, Some(4000));
+        assert!(host.coop);
+        assert_eq!(host.stage, Some(1));
+    }
+
+    #[test]
+    fn defaults_are_overridden() {
+        let defaults = Options { difficulty: Difficulty::Easy, width: 1920.0, ..Default::default() };
+        let options = Options::parse_with(defaults, vec!["-d".to_string(), "hard".to_string()]).unwrap();
+        assert_eq!(options.difficulty, Difficulty::Hard);
+        assert_eq!(options.width, 1920.0);
+    }
+
+    #[test]
+    fn unknown_flags() {
+        assert_eq!(parse(&["--seeed", "1"]).err(), Some(CliError::UnknownOption("--seeed".to_string())));
+        assert_eq!(parse(&["42"]).err(), Some(CliError::UnknownOption("42".to_string())));
+    }
+
+    #[test]
+    fn duplicate_flags_keep_the_last_value() {
+        let options = parse(&["--seed", "1", "--seed", "2", "-d", "easy", "--difficulty", "hard"]).unwrap();
+        assert_eq!(options.seed, Some(2));
+        assert_eq!(options.difficulty, Difficulty::Hard);
+        assert!(parse(&["--headless", "--headless"]).unwrap().headless);
+    }
+
+    #[test]
+    fn missing_and_invalid_values() {
+        assert_eq!(parse(&["--seed"]).err(), Some(CliError::MissingValue("--seed".to_string())));
+        let invalid = |option: &str, value: &str| Some(CliError::InvalidValue {
+            option: option.to_string(),
+            value: value.to_string(),
+        });
+        assert_eq!(parse(&["-s", "x"]).err(), invalid("-s", "x"));
+        assert_eq!(parse(&["--difficulty", "insane"]).err(), invalid("--difficulty", "insane"));
+        assert_eq!(parse(&["--width", "0"]).err(), invalid("--width", "0"));
+        assert_eq!(parse(&["--virtual", "1280"]).err(), invalid("--virtual", "1280"));
+        assert_eq!(parse(&["--stage", "4"]).err(), invalid("--stage", "4"));
+        assert_eq!(parse(&["--flight", "arcade,warp"]).err(), invalid("--flight", "arcade,warp"));
+        assert_eq!(parse(&["--host", "http"]).err(), invalid("--host", "http"));
+    }
+
+    #[test]
+    fn conflicting_flags() {
+        let conflicts: &[(&[&str], CliError)] = &[
+            (&["--record", "a.ron", "--playback", "b.ron"], CliError::Conflict("--record", "--playback")),
+            (&["--host", "4000", "--join", "localhost:4000"], CliError::Conflict("--host", "--join")),
+            (&["--host", "4000", "--record", "a.ron"], CliError::Conflict("--host", "--record")),
+            (&["--join", "localhost:4000", "--playback", "a.ron"], CliError::Conflict("--join", "--playback")),
+            (&["--bot", "--playback", "a.ron"], CliError::Conflict("--bot", "--playback")),
+            (&["--coop", "--record", "a.ron"], CliError::Conflict("--coop", "--record")),
+            (&["--coop", "--playback", "a.ron"], CliError::Conflict("--coop", "--playback")),
+            (&["--headless", "--fullscreen"], CliError::Conflict("--headless", "--fullscreen")),
+            (&["-f", "--headless"], CliError::Conflict("--headless", "--fullscreen")),
+        ];
+        for (args, conflict) in conflicts.iter() {
+            assert_eq!(parse(args).err().as_ref(), Some(conflict), "{:?}", args);
+        }
+    }
+}
@@ -0,0 +1,288 @@
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::fs;
+use std::path::Path;
+use std::collections::BTreeMap;
+use crate::{Aim, AimMode, BulletPattern, DifficultyCurves, Flight};
+
+/// Gameplay tuning, loaded from a RON file given with `--config`.
+/// Missing fields keep their default value.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct GameConfig {
+    pub duration: f32,
+    pub health: usize,
+    pub background: (f32, f32),
+    pub cannon: (f32, f32),
+    pub cannon_speed: f32,
+    /// Degrees per second the ship turns toward where it aims, in arcade flight.
+    pub cannon_turn_rate: f32,
+    /// Arcade or inertia flight per difficulty, and the handling of the inertia model.
+    pub flight: Flight,
+    pub invulnerability: f32,
+    pub knockback: f32,
+    pub ball: (f32, f32),
+    pub ball_speed: f32,
+    pub shot_interval: f32,
+    pub enemy_ball_speed: f32,
+    pub spacejunk: (f32, f32),
+    /// Shot junk breaks into `junk_pieces` (min, max) pieces, `junk_piece_scale` times its size and
+    /// `junk_split_speed` times faster, unless it is smaller than `junk_split_size` times `spacejunk`.
+    pub junk_split_size: f32,
+    pub junk_pieces: (usize, usize),
+    pub junk_piece_scale: f32,
+    pub junk_split_speed: f32,
+    /// How hard a shot pushes the junk it hits; junk weighs one per 1000 square units. 0 = not at all.
+    pub shot_mass: f32,
+    pub enemyship: (f32, f32),
+    pub enemyship_speed: f32,
+    /// Patterns enemy ships fire, and those of the special ones.
+    pub enemyship_pattern: String,
+    pub special_pattern: String,
+    /// How enemy ships aim, and the special ones.
+    pub enemyship_aim: Aim,
+    pub special_aim: Aim,
+    /// Bullet patterns by name, added to the built-in ones.
+    pub patterns: BTreeMap<String, BulletPattern>,
+    pub energy: (f32, f32),
+    pub num_of_energies: usize,
+    pub max_energies: usize,
+    pub energy_respawn: (f32, f32),
+    pub energy_lifetime: f32,
+    pub energy_blink: f32,
+    pub energy_clearance: f32,
+    pub score_per_kill: usize,
+    pub difficulty: DifficultyCurves,
+}
+
+impl Default for GameConfig {
+    fn default() -> Self {
+        GameConfig {
+            duration: 180.0,
+            health: 3,
+            background: (2048.0 * 1.5, 1536.0 * 1.5),
+            cannon: (200.0 * 0.4, 120.0 * 0.4),
+            cannon_speed: 500.0,
+            cannon_turn_rate: 720.0,
+            flight: Flight::default(),
+            invulnerability: 1.5,
+            knockback: 600.0,
+            ball: (16.0, 16.0),
+            ball_speed: 500.0,
+            shot_interval: 0.5,
+            enemy_ball_speed: 500.0,
+            spacejunk: (250.0, 198.0),
+            junk_split_size: 0.25,
+            junk_pieces: (2, 3),
+            junk_piece_scale: 0.6,
+            junk_split_speed: 1.3,
+            shot_mass: 2.0,
+            enemyship: (192.0 * 0.3, 250.0 * 0.3),
+            enemyship_speed: 100.0,
+            enemyship_pattern: "single".to_string(),
+            special_pattern: "fan3".to_string(),
+            enemyship_aim: Aim { mode: AimMode::Current, jitter: 8.0 },
+            special_aim: Aim { mode: AimMode::Fixed, jitter: 0.0 },
+            patterns: BulletPattern::builtin(),
+            energy: (64.0, 64.0),
+            num_of_energies: 5,
+            max_energies: 8,
+            energy_respawn: (8.0, 15.0),
+            energy_lifetime: 30.0,
+            energy_blink: 5.0,
+            energy_clearance: 200.0,
+            score_per_kill: 1,
+            difficulty: DifficultyCurves::default(),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum ConfigError {
+    Io(std::io::Error),
+    Parse(ron::Error),
+    Invalid(String),
+}
+
+impl fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ConfigError::Io(err) => write!(f, "can't read config: {}", err),
+            ConfigError::Parse(err) => write!(f, "can't parse config: {}", err),
+            ConfigError::Invalid(reason) => write!(f, "invalid config: {}", reason),
+        }
+    }
+}
+
+impl GameConfig {
+    pub fn load(path: &Path) -> Result<GameConfig, ConfigError> {
+        let text = fs::read_to_string(path).map_err(ConfigError::Io)?;
+        let mut config: GameConfig = ron::de::from_str(&text).map_err(ConfigError::Parse)?;
+        let mut patterns = BulletPattern::builtin();
+        patterns.extend(config.patterns);
+        config.patterns = patterns;
+        config.validate()?;
+        Ok(config)
+    }
+
+    /// Names of the fields whose value differs in `other`.
+    pub fn changed_fields(&self, other: &GameConfig) -> Vec<&'static str> {
+        // spelled out without `..` so a new field can't be left out here
+        let GameConfig {
+            duration, health, background, cannon, cannon_speed, cannon_turn_rate, flight, invulnerability,
+            knockback, ball, ball_speed, shot_interval, enemy_ball_speed, spacejunk, junk_split_size,
+            junk_pieces, junk_piece_scale, junk_split_speed, shot_mass, enemyship, enemyship_speed,
+            enemyship_pattern, special_pattern, enemyship_aim, special_aim, patterns, energy, num_of_energies,
+            max_energies, energy_respawn, energy_lifetime, energy_blink, energy_clearance, score_per_kill,
+            difficulty,
+        } = self;
+        let mut changed = Vec::new();
+        macro_rules! compare {
+            ($($field:ident),*) => {
+                $(if *$field != other.$field {
+                    changed.push(stringify!($field));
+                })*
+            };
+        }
+        compare!(
+            duration, health, background, cannon, cannon_speed, cannon_turn_rate, flight, invulnerability,
+            knockback, ball, ball_speed, shot_interval, enemy_ball_speed, spacejunk, junk_split_size,
+            junk_pieces, junk_piece_scale, junk_split_speed, shot_mass, enemyship, enemyship_speed,
+            enemyship_pattern, special_pattern, enemyship_aim, special_aim, patterns, energy, num_of_energies,
+            max_energies, energy_respawn, energy_lifetime, energy_blink, energy_clearance, score_per_kill,
+            difficulty
+        );
+        changed
+    }
+
+    pub fn validate(&self) -> Result<(), ConfigError> {
+        let sizes = [
+            ("background", self.background), ("cannon", self.cannon), ("ball", self.ball),
+            ("spacejunk", self.spacejunk), ("enemyship", self.enemyship), ("energy", self.energy),
+        ];
+        for (name, (x, y)) in sizes.iter() {
+            if !(*x > 0.0 && *y > 0.0) {
+                return Err(ConfigError::Invalid(format!("{} size must be positive", name)));
+            }
+        }
+        let values = [
+            ("duration", self.duration), ("cannon_speed", self.cannon_speed),
+            ("cannon_turn_rate", self.cannon_turn_rate), ("ball_speed", self.ball_speed),
+            ("shot_interval", self.shot_interval), ("enemy_ball_speed", self.enemy_ball_speed),
+            ("enemyship_speed", self.enemyship_speed), ("energy_respawn", self.energy_respawn.0),
+            ("energy_lifetime", self.energy_lifetime),
+        ];
+        for (name, value) in values.iter() {
+            if !(*value > 0.0 && value.is_finite()) {
+                return Err(ConfigError::Invalid(format!("{} must be positive", name)));
+            }
+        }
+        if self.junk_pieces.0 == 0 || self.junk_pieces.1 < self.junk_pieces.0 {
+            return Err(ConfigError::Invalid("junk_pieces must be (min, max), at least 1".to_string()));
+        }
+        if !(self.junk_piece_scale > 0.0 && self.junk_piece_scale < 1.0) {
+            return Err(ConfigError::Invalid("junk_piece_scale must be between 0 and 1".to_string()));
+        }
+        if !(self.junk_split_size >= 0.0 && self.junk_split_speed > 0.0) {
+            return Err(ConfigError::Invalid("junk_split_size and junk_split_speed must be positive".to_string()));
+        }
+        self.flight.validate().map_err(|reason| ConfigError::Invalid(format!("flight: {}", reason)))?;
+        if self.shot_mass < 0.0 {
+            return Err(ConfigError::Invalid("shot_mass can't be negative".to_string()));
+        }
+        if self.energy_respawn.1 < self.energy_respawn.0 {
+            return Err(ConfigError::Invalid("energy_respawn must be (min, max)".to_string()));
+        }
+        if self.invulnerability < 0.0 || self.knockback < 0.0 {
+            return Err(ConfigError::Invalid("invulnerability and knockback can't be negative".to_string()));
+        }
+        if self.energy_blink < 0.0 || self.energy_clearance < 0.0 {
+            return Err(ConfigError::Invalid("energy_blink and energy_clearance can't be negative".to_string()));
+        }
+        if self.health == 0 {
+            return Err(ConfigError::Invalid("health must be at least 1".to_string()));
+        }
+        for (name, pattern) in self.patterns.iter() {
+            pattern.validate().map_err(|reason| ConfigError::Invalid(format!("pattern {}: {}", name, reason)))?;
+        }
+        for (name, aim) in [("enemyship_aim", self.enemyship_aim), ("special_aim", self.special_aim)].iter() {
+            aim.validate().map_err(|reason| ConfigError::Invalid(format!("{}: {}", name, reason)))?;
+        }
+        for name in [&self.enemyship_pattern, &self.special_pattern].iter() {
+            if !self.patterns.contains_key(*name) {
+                return Err(ConfigError::Invalid(format!("unknown pattern '{}'", name)));
+            }
+        }
+        self.difficulty.validate().map_err(ConfigError::Invalid)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::PathBuf;
+
+    /// A config file of its own under the temp directory, removed when dropped.
+    struct TempFile(PathBuf);
+
+    impl TempFile {
+        fn new(name: &str, text: &str) -> TempFile {
+            let path = std::env::temp_dir().join(format!("shooting-{}-{}", std::process::id(), name));
+            fs::write(&path, text).unwrap();
+            TempFile(path)
+        }
+    }
+
+    impl Drop for TempFile {
+        fn drop(&mut self) {
+            let _ = fs::remove_file(&self.0);
+        }
+    }
+
+    #[test]
+    fn defaults_and_the_shipped_config_are_valid() {
+        assert!(GameConfig::default().validate().is_ok());
+        assert!(GameConfig::load(Path::new("config.ron")).is_ok());
+    }
+
+    #[test]
+    fn an_edit_keeps_the_rest_and_the_builtin_patterns() {
+        let file = TempFile::new("edit.ron", "(health: 7, patterns: { \"mine\": (kind: Ring, count: 3) })");
+        let config = GameConfig::load(&file.0).unwrap();
+        assert_eq!(config.health, 7);
+        assert_eq!(config.duration, GameConfig::default().duration);
+        assert!(config.patterns.contains_key("mine") && config.patterns.contains_key("spiral"));
+    }
+
+    #[test]
+    fn an_invalid_edit_is_rejected() {
+        let invalid = [
+            ("health.ron", "(health: 0)"),
+            ("duration.ron", "(duration: -1.0)"),
+            ("size.ron", "(cannon: (0.0, 40.0))"),
+            ("pieces.ron", "(junk_pieces: (3, 2))"),
+            ("pattern.ron", "(enemyship_pattern: \"nothing\")"),
+            ("split.ron", "(patterns: { \"bad\": (kind: Split(delay: 1.0, into: 0)) })"),
+        ];
+        for (name, text) in invalid.iter() {
+            let file = TempFile::new(name, text);
+            assert!(matches!(GameConfig::load(&file.0), Err(ConfigError::Invalid(_))), "{}", text);
+        }
+        let file = TempFile::new("broken.ron", "(health: ");
+        assert!(matches!(GameConfig::load(&file.0), Err(ConfigError::Parse(_))));
+        assert!(matches!(GameConfig::load(Path::new("no such config.ron")), Err(ConfigError::Io(_))));
+    }
+
+    #[test]
+    fn only_changed_fields_are_reported() {
+        let config = GameConfig::default();
+        assert!(config.changed_fields(&config.clone()).is_empty());
+
+        let mut other = config.clone();
+        other.health += 1;
+        other.cannon = (1.0, 2.0);
+        other.patterns.insert("mine".to_string(), BulletPattern::default());
+        assert_eq!(config.changed_fields(&other), vec!["health", "cannon", "patterns"]);
+        assert_eq!(other.changed_fields(&config), vec!["health", "cannon", "patterns"]);
+    }
+}
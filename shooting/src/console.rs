@@ -0,0 +1,515 @@
+use bevy::prelude::*;
+use bevy::window::ReceivedCharacter;
+use rand::{rngs::StdRng, SeedableRng};
+use std::time::Duration;
+use crate::{Aim, AimMode, AppState, Cannon, Collider, DifficultyLevel, Enemies, EnemyShips, Energy, GameConfig,
+    GameEvent, GameRng, MainTimer, Options, Params, Scoreboard};
+
+/// Lines of output kept on screen.
+const SCROLLBACK: usize = 16;
+
+const HELP: &str = "\
+spawn junk [X Y]        a piece of junk, from a random side or at X Y
+spawn enemyship X Y [P] [A]
+                        an enemy ship at X Y flying at the nearest player, firing pattern P,
+                        aiming A: fixed, current or predictive
+spawn energy X Y        an energy at X Y
+god                     turn invulnerability on or off
+set health|score N [P]  health or score of every player, or of player P (1-2)
+time N                  N seconds left
+stage N                 jump to the start of stage N (1-3)
+seed N                  reseed the random generator
+state start|ingame|finish|settings|achievements
+                        a running game only ends with finish
+clear                   clear the console
+(0 0 is the center of the field, y goes up)";
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SetTarget {
+    Health,
+    Score,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum ConsoleCommand {
+    /// `pattern` and `aim` are for a spawned ship.
+    Spawn { kind: Collider, pos: Option<Vec2>, pattern: Option<String>, aim: Option<AimMode> },
+    God,
+    Set { target: SetTarget, value: usize, player: Option<usize> },
+    Time(f32),
+    Stage(u32),
+    Seed(u64),
+    State(AppState),
+    Help,
+    Clear,
+}
+
+fn number<T: std::str::FromStr>(word: Option<&str>, what: &str) -> Result<T, String> {
+    let word = word.ok_or_else(|| format!("missing {}", what))?;
+    word.parse().map_err(|_| format!("invalid {} '{}'", what, word))
+}
+
+impl ConsoleCommand {
+    pub fn parse(line: &str) -> Result<ConsoleCommand, String> {
+        let mut words = line.split_whitespace();
+        let name = words.next().unwrap_or("");
+        let command = match name {
+            "spawn" => {
+                let kind = match words.next() {
+                    Some("junk") | Some("spacejunk") => Collider::Spacejunk,
+                    Some("enemyship") | Some("ship") => Collider::Enemyship,
+                    Some("energy") => Collider::Energy,
+                    Some(other) => return Err(format!("can't spawn '{}'", other)),
+                    None => return Err("spawn what? junk, enemyship or energy".to_string()),
+                };
+                let pos = match words.next() {
+                    Some(x) => Some(Vec2::new(number(Some(x), "x")?, number(words.next(), "y")?)),
+                    None if kind == Collider::Spacejunk => None,
+                    None => return Err("missing position".to_string()),
+                };
+                let (pattern, aim) = match kind {
+                    Collider::Enemyship => (words.next().map(|pattern| pattern.to_string()), match words.next() {
+                        Some("fixed") => Some(AimMode::Fixed),
+                        Some("current") => Some(AimMode::Current),
+                        Some("predictive") => Some(AimMode::Predictive),
+                        Some(other) => return Err(format!("unknown aim '{}', fixed, current or predictive", other)),
+                        None => None,
+                    }),
+                    _ => (None, None),
+                };
+                ConsoleCommand::Spawn { kind, pos, pattern, aim }
+            }
+            "god" => ConsoleCommand::God,
+            "set" => {
+                let target = match words.next() {
+                    Some("health") => SetTarget::Health,
+                    Some("score") => SetTarget::Score,
+                    Some(other) => return Err(format!("can't set '{}'", other)),
+                    None => return Err("set what? health or score".to_string()),
+                };
+                let value = number(words.next(), "value")?;
+                let player = match words.next() {
+                    Some(player) => match player.parse::<usize>() {
+                        Ok(player) if player >= 1 => Some(player - 1),
+                        _ => return Err(format!("invalid player '{}'", player)),
+                    },
+                    None => None,
+                };
+                ConsoleCommand::Set { target, value, player }
+            }
+            "time" => {
+                let seconds: f32 = number(words.next(), "time")?;
+                if !(seconds >= 0.0) {
+                    return Err(format!("invalid time '{}'", seconds));
+                }
+                ConsoleCommand::Time(seconds)
+            }
+            "stage" => {
+                let stage = number(words.next(), "stage")?;
+                if !(1..=Options::STAGES).contains(&stage) {
+                    return Err(format!("stage must be 1-{}", Options::STAGES));
+                }
+                ConsoleCommand::Stage(stage)
+            }
+            "seed" => ConsoleCommand::Seed(number(words.next(), "seed")?),
+            "state" => ConsoleCommand::State(match words.next() {
+                Some("start") | Some("menu") => AppState::Start,
+                Some("ingame") | Some("game") => AppState::InGame,
+                Some("finish") => AppState::Finish,
+                Some("settings") | Some("options") => AppState::Settings,
+                Some("achievements") => AppState::Achievements,
+                Some(other) => return Err(format!("unknown state '{}'", other)),
+                None => return Err("missing state".to_string()),
+            }),
+            "help" => ConsoleCommand::Help,
+            "clear" => ConsoleCommand::Clear,
+            _ => return Err(format!("unknown command '{}', try help", name)),
+        };
+        match words.next() {
+            Some(extra) => Err(format!("unexpected '{}'", extra)),
+            None => Ok(command),
+        }
+    }
+}
+
+/// The developer console, opened with the backtick key. While it is open
+/// the keyboard and mouse only go to the console.
+#[derive(Default)]
+pub struct Console {
+    pub open: bool,
+    line: String,
+    output: Vec<String>,
+    /// Entered lines, for Up to bring back.
+    history: Vec<String>,
+    recall: usize,
+    pending: Vec<String>,
+    /// Hits don't cost health.
+    pub god: bool,
+    /// The current game was changed from the console, so it doesn't count for achievements.
+    pub cheated: bool,
+}
+
+/// The console background, with the `ConsoleText` in it.
+pub struct ConsolePanel;
+
+pub struct ConsoleText;
+
+impl Console {
+    fn print(&mut self, text: String) {
+        self.output.extend(text.lines().map(|line| line.to_string()));
+        if self.output.len() > SCROLLBACK {
+            let extra = self.output.len() - SCROLLBACK;
+            self.output.drain(..extra);
+        }
+    }
+
+    /// Runs before the game reads the devices and swallows the input while the console is open.
+    pub fn capture(mut console: ResMut<Console>, mut keyboard_input: ResMut<Input<KeyCode>>,
+        mut mouse_input: ResMut<Input<MouseButton>>, mut characters: EventReader<ReceivedCharacter>)
+    {
+        if keyboard_input.just_pressed(KeyCode::Grave) {
+            console.open = !console.open;
+        }
+        // read even while closed, so what was typed before doesn't show up when it opens
+        let typed: String = characters.iter()
+            .map(|event| event.char)
+            .filter(|c| *c != '`' && !c.is_control())
+            .collect();
+        if !console.open {
+            return;
+        }
+        console.line.push_str(&typed);
+        if keyboard_input.just_pressed(KeyCode::Back) {
+            console.line.pop();
+        }
+        if keyboard_input.just_pressed(KeyCode::Up) && console.recall > 0 {
+            console.recall -= 1;
+            console.line = console.history[console.recall].clone();
+        }
+        if keyboard_input.just_pressed(KeyCode::Return) {
+            let line = std::mem::take(&mut console.line).trim().to_string();
+            if !line.is_empty() {
+                console.history.push(line.clone());
+                console.pending.push(line);
+            }
+            console.recall = console.history.len();
+        }
+
+        let keys: Vec<KeyCode> = keyboard_input.get_pressed()
+            .chain(keyboard_input.get_just_pressed())
+            .copied()
+            .collect();
+        for key in keys {
+            keyboard_input.reset(key);
+        }
+        let buttons: Vec<MouseButton> = mouse_input.get_pressed()
+            .chain(mouse_input.get_just_pressed())
+            .copied()
+            .collect();
+        for button in buttons {
+            mouse_input.reset(button);
+        }
+    }
+
+    pub fn execute(mut commands: Commands, mut console: ResMut<Console>, mut state: ResMut<State<AppState>>,
+        mut scoreboard: ResMut<Scoreboard>, mut timer: ResMut<MainTimer>, config: Res<GameConfig>,
+        mut rng: ResMut<GameRng>, params: Res<Params>, mut materials: ResMut<Assets<ColorMaterial>>,
+        level: Res<DifficultyLevel>, cannons: Query<&Transform, With<Cannon>>, mut events: EventWriter<GameEvent>)
+    {
+        if console.pending.is_empty() {
+            return;
+        }
+        let in_game = *state.current() == AppState::InGame;
+        for line in std::mem::take(&mut console.pending) {
+            console.print(format!("> {}", line));
+            let command = match ConsoleCommand::parse(&line) {
+                Ok(command) => command,
+                Err(err) => {
+                    console.print(format!("error: {}", err));
+                    continue;
+                }
+            };
+            let counts = !matches!(command, ConsoleCommand::Help);
+            let needs_game = !matches!(command,
+                ConsoleCommand::God | ConsoleCommand::Seed(_) | ConsoleCommand::State(_)
+                    | ConsoleCommand::Help | ConsoleCommand::Clear);
+            if needs_game && !in_game {
+                console.print("error: no game running".to_string());
+                continue;
+            }
+
+            let message = match command {
+                ConsoleCommand::Spawn { kind, pos, pattern, aim } => {
+                    let pattern = pattern.unwrap_or_else(|| config.enemyship_pattern.clone());
+                    if !config.patterns.contains_key(&pattern) {
+                        let names: Vec<&str> = config.patterns.keys().map(|name| name.as_str()).collect();
+                        console.print(format!("error: unknown pattern '{}', there are {}", pattern, names.join(", ")));
+                        continue;
+                    }
+                    match kind {
+                        Collider::Spacejunk => Enemies::spawn(&mut commands, &mut materials, &params, &mut rng.0,
+                            &level.0, pos),
+                        Collider::Enemyship => {
+                            let pos = pos.unwrap_or_default();
+                            // aim at the nearest player, like the ships that come on their own
+                            let target = cannons.iter()
+                                .map(|transform| transform.translation.truncate())
+                                .min_by(|a, b| a.distance(pos).partial_cmp(&b.distance(pos)).unwrap())
+                                .unwrap_or_default();
+                            EnemyShips::spawn(&mut commands, &mut materials, &params, pos, target,
+                                config.enemyship_speed * level.0.ship_speed, pattern,
+                                Aim { mode: aim.unwrap_or(config.enemyship_aim.mode), ..config.enemyship_aim });
+                        }
+                        _ => Energy::spawn(&mut commands, pos.unwrap_or_default(), &params, &config),
+                    }
+                    events.send(GameEvent::Spawned(kind));
+                    format!("spawned {:?}", kind)
+                }
+                ConsoleCommand::God => {
+                    console.god = !console.god;
+                    if console.god {
+                        "god mode on".to_string()
+                    } else {
+                        "god mode off".to_string()
+                    }
+                }
+                ConsoleCommand::Set { target, value, player } => {
+                    let count = scoreboard.players.len();
+                    if player.is_some_and(|player| player >= count) {
+                        console.print(format!("error: there are {} players", count));
+                        continue;
+                    }
+                    for (i, score) in scoreboard.players.iter_mut().enumerate() {
+                        if player.is_none_or(|player| player == i) {
+                            match target {
+                                SetTarget::Health => score.health = value,
+                                SetTarget::Score => score.score = value,
+                            }
+                        }
+                    }
+                    format!("{:?} set to {}", target, value)
+                }
+                ConsoleCommand::Time(seconds) => {
+                    let left = seconds.min(config.duration);
+                    timer.0.set_elapsed(Duration::from_secs_f32(config.duration - left));
+                    format!("{} s left", left)
+                }
+                ConsoleCommand::Stage(stage) => {
+                    let stage_secs = config.duration / Options::STAGES as f32;
+                    timer.0.set_elapsed(Duration::from_secs_f32(stage_secs * (stage - 1) as f32));
+                    format!("stage {}", stage)
+                }
+                ConsoleCommand::Seed(seed) => {
+                    rng.0 = StdRng::seed_from_u64(seed);
+                    format!("random seed: {}", seed)
+                }
+                // a game ends through the game over check, which cleans up after it
+                ConsoleCommand::State(AppState::Finish) if in_game => {
+                    timer.0.set_elapsed(Duration::from_secs_f32(config.duration));
+                    "state Finish".to_string()
+                }
+                ConsoleCommand::State(_) if in_game => {
+                    console.print("error: a game is running, leave it with 'state finish'".to_string());
+                    continue;
+                }
+                ConsoleCommand::State(_) if *state.current() == AppState::Remote => {
+                    console.print("error: the host runs this game".to_string());
+                    continue;
+                }
+                ConsoleCommand::State(target) => match state.set(target.clone()) {
+                    Ok(()) => format!("state {:?}", target),
+                    Err(err) => {
+                        console.print(format!("error: {:?}", err));
+                        continue;
+                    }
+                },
+                ConsoleCommand::Help => HELP.to_string(),
+                ConsoleCommand::Clear => {
+                    console.output.clear();
+                    continue;
+                }
+            };
+            if counts {
+                console.cheated = true;
+            }
+            console.print(message);
+        }
+    }
+
+    /// A game starts clean, unless god mode is still on.
+    pub fn start_session(mut console: ResMut<Console>) {
+        console.cheated = console.god;
+    }
+
+    pub fn show(mut commands: Commands, console: Res<Console>, asset_server: Res<AssetServer>,
+        mut materials: ResMut<Assets<ColorMaterial>>, panels: Query<Entity, With<ConsolePanel>>,
+        mut texts: Query<&mut Text, With<ConsoleText>>)
+    {
+        if !console.is_changed() {
+            return;
+        }
+        if !console.open {
+            for entity in panels.iter() {
+                commands.entity(entity).despawn_recursive();
+            }
+            return;
+        }
+        let mut value = console.output.join("\n");
+        if !value.is_empty() {
+            value.push('\n');
+        }
+        value.push_str(&format!("> {}_", console.line));
+        if let Ok(mut text) = texts.single_mut() {
+            text.sections[0].value = value;
+            return;
+        }
+        commands.spawn_bundle(NodeBundle {
+            style: Style {
+                position_type: PositionType::Absolute,
+                position: Rect {
+                    top: Val::Px(0.0),
+                    left: Val::Px(0.0),
+                    ..Default::default()
+                },
+                size: Size::new(Val::Percent(100.0), Val::Auto),
+                padding: Rect::all(Val::Px(10.0)),
+                ..Default::default()
+            },
+            material: materials.add(Color::rgba(0.0, 0.0, 0.0, 0.8).into()),
+            ..Default::default()
+        })
+        .insert(ConsolePanel)
+        .with_children(|parent| {
+            parent.spawn_bundle(TextBundle {
+                text: Text::with_section(
+                    value,
+                    TextStyle {
+                        font: asset_server.load("fonts/FiraMono-Medium.ttf"),
+                        font_size: 20.0,
+                        color: Color::rgb(0.9, 0.9, 0.9),
+                    },
+                    Default::default(),
+                ),
+                ..Default::default()
+            })
+            .insert(ConsoleText);
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn err(line: &str) -> String {
+        ConsoleCommand::parse(line).unwrap_err()
+    }
+
+    fn spawn(kind: Collider, pos: Option<(f32, f32)>) -> ConsoleCommand {
+        let pos = pos.map(|(x, y)| Vec2::new(x, y));
+        ConsoleCommand::Spawn { kind, pos, pattern: None, aim: None }
+    }
+
+    #[test]
+    fn spawn_commands() {
+        assert_eq!(ConsoleCommand::parse("spawn junk"), Ok(spawn(Collider::Spacejunk, None)));
+        assert_eq!(ConsoleCommand::parse("spawn spacejunk -100 50.5"),
+            Ok(spawn(Collider::Spacejunk, Some((-100.0, 50.5)))));
+        assert_eq!(ConsoleCommand::parse("spawn ship 0 200"), Ok(spawn(Collider::Enemyship, Some((0.0, 200.0)))));
+        assert_eq!(ConsoleCommand::parse("  spawn   energy 1 2  "), Ok(spawn(Collider::Energy, Some((1.0, 2.0)))));
+        assert_eq!(ConsoleCommand::parse("spawn enemyship 0 200 spiral predictive"), Ok(ConsoleCommand::Spawn {
+            kind: Collider::Enemyship,
+            pos: Some(Vec2::new(0.0, 200.0)),
+            pattern: Some("spiral".to_string()),
+            aim: Some(AimMode::Predictive),
+        }));
+    }
+
+    #[test]
+    fn spawn_errors() {
+        assert_eq!(err("spawn"), "spawn what? junk, enemyship or energy");
+        assert_eq!(err("spawn boss 0 0"), "can't spawn 'boss'");
+        assert_eq!(err("spawn energy"), "missing position");
+        assert_eq!(err("spawn enemyship 10"), "missing y");
+        assert_eq!(err("spawn junk left 0"), "invalid x 'left'");
+        assert_eq!(err("spawn junk 0 up"), "invalid y 'up'");
+        assert_eq!(err("spawn energy 0 0 fan"), "unexpected 'fan'");
+        assert_eq!(err("spawn enemyship 0 0 fan sideways"), "unknown aim 'sideways', fixed, current or predictive");
+        assert_eq!(err("spawn enemyship 0 0 fan fixed more"), "unexpected 'more'");
+    }
+
+    #[test]
+    fn god_help_clear() {
+        assert_eq!(ConsoleCommand::parse("god"), Ok(ConsoleCommand::God));
+        assert_eq!(ConsoleCommand::parse("help"), Ok(ConsoleCommand::Help));
+        assert_eq!(ConsoleCommand::parse("clear"), Ok(ConsoleCommand::Clear));
+        assert_eq!(err("god on"), "unexpected 'on'");
+        assert_eq!(err("clear all"), "unexpected 'all'");
+    }
+
+    #[test]
+    fn set() {
+        assert_eq!(ConsoleCommand::parse("set health 5"),
+            Ok(ConsoleCommand::Set { target: SetTarget::Health, value: 5, player: None }));
+        assert_eq!(ConsoleCommand::parse("set score 1200 2"),
+            Ok(ConsoleCommand::Set { target: SetTarget::Score, value: 1200, player: Some(1) }));
+        assert_eq!(err("set"), "set what? health or score");
+        assert_eq!(err("set lives 3"), "can't set 'lives'");
+        assert_eq!(err("set health"), "missing value");
+        assert_eq!(err("set health -1"), "invalid value '-1'");
+        assert_eq!(err("set health lots"), "invalid value 'lots'");
+        assert_eq!(err("set score 10 0"), "invalid player '0'");
+        assert_eq!(err("set score 10 two"), "invalid player 'two'");
+        assert_eq!(err("set score 10 1 2"), "unexpected '2'");
+    }
+
+    #[test]
+    fn time() {
+        assert_eq!(ConsoleCommand::parse("time 30"), Ok(ConsoleCommand::Time(30.0)));
+        assert_eq!(ConsoleCommand::parse("time 0"), Ok(ConsoleCommand::Time(0.0)));
+        assert_eq!(err("time"), "missing time");
+        assert_eq!(err("time soon"), "invalid time 'soon'");
+        assert_eq!(err("time -5"), "invalid time '-5'");
+        assert_eq!(err("time NaN"), "invalid time 'NaN'");
+        assert_eq!(err("time 5 10"), "unexpected '10'");
+    }
+
+    #[test]
+    fn stage() {
+        assert_eq!(ConsoleCommand::parse("stage 1"), Ok(ConsoleCommand::Stage(1)));
+        assert_eq!(ConsoleCommand::parse("stage 3"), Ok(ConsoleCommand::Stage(3)));
+        assert_eq!(err("stage"), "missing stage");
+        assert_eq!(err("stage 0"), "stage must be 1-3");
+        assert_eq!(err("stage 4"), "stage must be 1-3");
+        assert_eq!(err("stage two"), "invalid stage 'two'");
+    }
+
+    #[test]
+    fn seed() {
+        assert_eq!(ConsoleCommand::parse("seed 42"), Ok(ConsoleCommand::Seed(42)));
+        assert_eq!(err("seed"), "missing seed");
+        assert_eq!(err("seed 4.2"), "invalid seed '4.2'");
+        assert_eq!(err("seed 1 2"), "unexpected '2'");
+    }
+
+    #[test]
+    fn state() {
+        assert_eq!(ConsoleCommand::parse("state start"), Ok(ConsoleCommand::State(AppState::Start)));
+        assert_eq!(ConsoleCommand::parse("state menu"), Ok(ConsoleCommand::State(AppState::Start)));
+        assert_eq!(ConsoleCommand::parse("state ingame"), Ok(ConsoleCommand::State(AppState::InGame)));
+        assert_eq!(ConsoleCommand::parse("state game"), Ok(ConsoleCommand::State(AppState::InGame)));
+        assert_eq!(ConsoleCommand::parse("state finish"), Ok(ConsoleCommand::State(AppState::Finish)));
+        assert_eq!(ConsoleCommand::parse("state options"), Ok(ConsoleCommand::State(AppState::Settings)));
+        assert_eq!(ConsoleCommand::parse("state achievements"), Ok(ConsoleCommand::State(AppState::Achievements)));
+        assert_eq!(err("state"), "missing state");
+        assert_eq!(err("state paused"), "unknown state 'paused'");
+        assert_eq!(err("state finish now"), "unexpected 'now'");
+    }
+
+    #[test]
+    fn unknown_commands() {
+        assert_eq!(err("fly 1 2"), "unknown command 'fly', try help");
+        assert_eq!(err("GOD"), "unknown command 'GOD', try help");
+        assert_eq!(err(""), "unknown command '', try help");
+    }
+}
@@ -0,0 +1,69 @@
+use bevy::prelude::*;
+use bevy::render::camera::OrthographicProjection;
+use crate::SessionEntity;
+
+pub struct MainCamera;
+
+pub struct CursorWorld(pub Option<Vec2>);
+
+pub struct Crosshair;
+
+impl Crosshair {
+    pub fn setup(mut commands: Commands, mut materials: ResMut<Assets<ColorMaterial>>) {
+        let material = materials.add(Color::rgba(1.0, 0.3, 0.3, 0.8).into());
+        commands.spawn_bundle((Transform::from_xyz(0.0, 0.0, 10.0), GlobalTransform::identity()))
+        .insert(Crosshair)
+        .insert(SessionEntity)
+        .with_children(|parent| {
+            parent.spawn_bundle(SpriteBundle {
+                material: material.clone(),
+                sprite: Sprite::new(Vec2::new(24.0, 2.0)),
+                ..Default::default()
+            });
+            parent.spawn_bundle(SpriteBundle {
+                material,
+                sprite: Sprite::new(Vec2::new(2.0, 24.0)),
+                ..Default::default()
+            });
+        });
+    }
+
+    pub fn update(cursor: Res<CursorWorld>, mut query: Query<&mut Transform, With<Crosshair>>) {
+        if let (Some(pos), Ok(mut transform)) = (cursor.0, query.single_mut()) {
+            transform.translation.x = pos.x;
+            transform.translation.y = pos.y;
+        }
+    }
+
+    pub fn hide_cursor(mut windows: ResMut<Windows>) {
+        if let Some(window) = windows.get_primary_mut() {
+            window.set_cursor_visibility(false);
+        }
+    }
+
+    pub fn show_cursor(mut windows: ResMut<Windows>) {
+        if let Some(window) = windows.get_primary_mut() {
+            window.set_cursor_visibility(true);
+        }
+    }
+}
+
+/// Converts a cursor position in window pixels into world coordinates as seen by `camera`.
+pub fn cursor_to_world(window: &Window, camera: &GlobalTransform, projection: &OrthographicProjection)
+    -> Option<Vec2>
+{
+    let cursor = window.cursor_position()?;
+    let size = Vec2::new(window.width(), window.height());
+    let p = (cursor - size * 0.5) * projection.scale;
+    let world = camera.compute_matrix() * p.extend(0.0).extend(1.0);
+    Some(Vec2::new(world.x, world.y))
+}
+
+pub fn cursor_world_system(windows: Res<Windows>, mut cursor: ResMut<CursorWorld>,
+    camera: Query<(&GlobalTransform, &OrthographicProjection), With<MainCamera>>)
+{
+    cursor.0 = match (windows.get_primary(), camera.single()) {
+        (Some(window), Ok((transform, projection))) => cursor_to_world(window, transform, projection),
+        _ => None,
+    };
+}
@@ -0,0 +1,198 @@
+use bevy::prelude::*;
+use bevy::diagnostic::{Diagnostics, FrameTimeDiagnosticsPlugin};
+use crate::{AppState, Ball, Cannon, Collider, Enemy, EnemyShip, EnemyShipTimer, EnemyShotTimer, EnemyTimer,
+    EnergyTimer, Particle};
+
+/// Thickness of the hitbox outlines, in world units.
+const LINE: f32 = 2.0;
+
+/// Developer overlay, toggled with F3: hitbox outlines and a panel with FPS,
+/// entity counts, spawn timers and the current state.
+#[derive(Default)]
+pub struct DebugOverlay {
+    pub visible: bool,
+}
+
+/// The text panel of the overlay.
+pub struct DebugText;
+
+/// One side of a hitbox outline; the sides are moved to this frame's hitboxes, not respawned.
+pub struct DebugOutline;
+
+pub struct DebugMaterials {
+    ship: Handle<ColorMaterial>,
+    spacejunk: Handle<ColorMaterial>,
+    enemyship: Handle<ColorMaterial>,
+    enemyball: Handle<ColorMaterial>,
+    selfball: Handle<ColorMaterial>,
+    energy: Handle<ColorMaterial>,
+    /// Full sprite bounds of junk and ships, which is what the player's shots hit.
+    shot_box: Handle<ColorMaterial>,
+}
+
+impl FromWorld for DebugMaterials {
+    fn from_world(world: &mut World) -> Self {
+        let mut materials = world.get_resource_mut::<Assets<ColorMaterial>>().unwrap();
+        DebugMaterials {
+            ship: materials.add(Color::rgb(1.0, 1.0, 1.0).into()),
+            spacejunk: materials.add(Color::rgb(1.0, 0.6, 0.1).into()),
+            enemyship: materials.add(Color::rgb(1.0, 0.2, 0.2).into()),
+            enemyball: materials.add(Color::rgb(1.0, 0.2, 1.0).into()),
+            selfball: materials.add(Color::rgb(0.2, 1.0, 1.0).into()),
+            energy: materials.add(Color::rgb(0.2, 1.0, 0.2).into()),
+            shot_box: materials.add(Color::rgba(1.0, 1.0, 1.0, 0.3).into()),
+        }
+    }
+}
+
+impl DebugMaterials {
+    fn collider(&self, collider: Collider) -> Handle<ColorMaterial> {
+        match collider {
+            Collider::Spacejunk => self.spacejunk.clone(),
+            Collider::Enemyship => self.enemyship.clone(),
+            Collider::Enemyball => self.enemyball.clone(),
+            Collider::Selfball => self.selfball.clone(),
+            Collider::Energy => self.energy.clone(),
+        }
+    }
+}
+
+/// The four sides of the outline of a box: where each goes and its size.
+fn outline(center: Vec3, size: Vec2) -> [(Vec3, Vec2); 4] {
+    let half = size * 0.5;
+    let side = |x: f32, y: f32| Vec3::new(center.x + x, center.y + y, 10.0);
+    [
+        (side(0.0, half.y), Vec2::new(size.x + LINE, LINE)),
+        (side(0.0, -half.y), Vec2::new(size.x + LINE, LINE)),
+        (side(-half.x, 0.0), Vec2::new(LINE, size.y + LINE)),
+        (side(half.x, 0.0), Vec2::new(LINE, size.y + LINE)),
+    ]
+}
+
+fn remaining(timer: Option<&Timer>) -> String {
+    match timer {
+        Some(timer) => format!("{:.1}s", (timer.duration().as_secs_f32() - timer.elapsed_secs()).max(0.0)),
+        None => "-".to_string(),
+    }
+}
+
+impl DebugOverlay {
+    pub fn toggle(mut commands: Commands, keyboard_input: Res<Input<KeyCode>>, asset_server: Res<AssetServer>,
+        mut overlay: ResMut<DebugOverlay>, texts: Query<Entity, With<DebugText>>)
+    {
+        if !keyboard_input.just_pressed(KeyCode::F3) {
+            return;
+        }
+        overlay.visible = !overlay.visible;
+        if !overlay.visible {
+            for entity in texts.iter() {
+                commands.entity(entity).despawn_recursive();
+            }
+            return;
+        }
+        commands.spawn_bundle(TextBundle {
+            text: Text::with_section(
+                "",
+                TextStyle {
+                    font: asset_server.load("fonts/FiraMono-Medium.ttf"),
+                    font_size: 20.0,
+                    color: Color::rgb(0.2, 1.0, 0.2),
+                },
+                Default::default(),
+            ),
+            style: Style {
+                position_type: PositionType::Absolute,
+                position: Rect {
+                    bottom: Val::Px(20.0),
+                    left: Val::Px(20.0),
+                    ..Default::default()
+                },
+                ..Default::default()
+            },
+            ..Default::default()
+        })
+        .insert(DebugText);
+    }
+
+    /// Outlines what each collider really collides with: the shrunk boxes `Cannon::collision` uses
+    /// and, faint, the full sprite bounds the player's shots use.
+    pub fn outlines(mut commands: Commands, overlay: Res<DebugOverlay>, materials: Res<DebugMaterials>,
+        mut old: Query<(Entity, &mut Transform, &mut Sprite, &mut Handle<ColorMaterial>), With<DebugOutline>>,
+        colliders: Query<(&Sprite, &Transform, &Collider), Without<DebugOutline>>,
+        ships: Query<(&Sprite, &Transform), (With<Cannon>, Without<DebugOutline>)>)
+    {
+        let mut boxes = Vec::new();
+        if overlay.visible {
+            for (sprite, transform) in ships.iter() {
+                boxes.push((transform.translation, sprite.size * Cannon::HITBOX, materials.ship.clone()));
+            }
+            for (sprite, transform, collider) in colliders.iter() {
+                let size = sprite.size * Cannon::hitbox(*collider);
+                boxes.push((transform.translation, size, materials.collider(*collider)));
+                if *collider == Collider::Spacejunk || *collider == Collider::Enemyship {
+                    boxes.push((transform.translation, sprite.size, materials.shot_box.clone()));
+                }
+            }
+        }
+
+        // the sides of last frame go to the first boxes, new ones are spawned and the rest despawned
+        let mut old = old.iter_mut();
+        for (center, size, material) in boxes {
+            for (translation, side) in outline(center, size).iter() {
+                match old.next() {
+                    Some((_, mut transform, mut sprite, mut handle)) => {
+                        transform.translation = *translation;
+                        sprite.size = *side;
+                        if *handle != material {
+                            *handle = material.clone();
+                        }
+                    }
+                    None => {
+                        commands.spawn_bundle(SpriteBundle {
+                            material: material.clone(),
+                            transform: Transform::from_translation(*translation),
+                            sprite: Sprite::new(*side),
+                            ..Default::default()
+                        })
+                        .insert(DebugOutline);
+                    }
+                }
+            }
+        }
+        for (entity, ..) in old {
+            commands.entity(entity).despawn();
+        }
+    }
+
+    pub fn update_text(overlay: Res<DebugOverlay>, diagnostics: Res<Diagnostics>, state: Res<State<AppState>>,
+        mut texts: Query<&mut Text, With<DebugText>>, entities: Query<Entity, Without<DebugOutline>>,
+        colliders: Query<&Collider>,
+        balls: Query<&Ball>, particles: Query<&Particle>, enemies: Query<&Enemy>, enemyships: Query<&EnemyShip>,
+        junk_timer: Query<&Timer, With<EnemyTimer>>, ship_timer: Query<&Timer, With<EnemyShipTimer>>,
+        shot_timer: Query<&Timer, With<EnemyShotTimer>>, energy_timer: Query<&Timer, With<EnergyTimer>>)
+    {
+        if !overlay.visible {
+            return;
+        }
+        let mut text = match texts.single_mut() {
+            Ok(text) => text,
+            Err(_) => return,
+        };
+        let fps = diagnostics.get(FrameTimeDiagnosticsPlugin::FPS)
+            .and_then(|fps| fps.average())
+            .unwrap_or(0.0);
+        let count = |kind: Collider| colliders.iter().filter(|collider| **collider == kind).count();
+        text.sections[0].value = format!(
+            "FPS: {:.0}\nState: {:?}\nEntities: {}\n\
+            Ball: {}  Particle: {}  Enemy: {}  EnemyShip: {}\n\
+            Colliders: junk {}  ship {}  enemy ball {}  self ball {}  energy {}\n\
+            Next spawn: junk {}  ship {}  shot {}  energy {}",
+            fps, state.current(), entities.iter().count(),
+            balls.iter().count(), particles.iter().count(), enemies.iter().count(), enemyships.iter().count(),
+            count(Collider::Spacejunk), count(Collider::Enemyship), count(Collider::Enemyball),
+            count(Collider::Selfball), count(Collider::Energy),
+            remaining(junk_timer.iter().next()), remaining(ship_timer.iter().next()),
+            remaining(shot_timer.iter().next()), remaining(energy_timer.iter().next()),
+        );
+    }
+}
@@ -0,0 +1,217 @@
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::str::FromStr;
+use crate::{GameConfig, MainTimer};
+
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Serialize, Deserialize)]
+pub enum Difficulty {
+    Easy,
+    Normal,
+    Hard,
+}
+
+impl FromStr for Difficulty {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "easy" => Ok(Difficulty::Easy),
+            "normal" => Ok(Difficulty::Normal),
+            "hard" => Ok(Difficulty::Hard),
+            _ => Err(()),
+        }
+    }
+}
+
+/// Spawn and shot tuning at a given time of the session.
+/// Intervals are in seconds, speeds are multipliers of the configured base speeds.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct CurvePoint {
+    pub time: f32,
+    pub junk_interval: (f32, f32),
+    pub ship_interval: (f32, f32),
+    pub shot_interval: f32,
+    pub junk_speed: f32,
+    pub ship_speed: f32,
+}
+
+/// One curve per difficulty preset, each a list of points sorted by time.
+/// Values are interpolated between points and held after the last one.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct DifficultyCurves {
+    pub easy: Vec<CurvePoint>,
+    pub normal: Vec<CurvePoint>,
+    pub hard: Vec<CurvePoint>,
+}
+
+impl Default for DifficultyCurves {
+    fn default() -> Self {
+        let point = |time, junk_interval, ship_interval, shot_interval, junk_speed, ship_speed| CurvePoint {
+            time, junk_interval, ship_interval, shot_interval, junk_speed, ship_speed,
+        };
+        DifficultyCurves {
+            easy: vec![
+                point(0.0, (2.0, 5.0), (8.0, 12.0), 1.5, 0.8, 0.8),
+                point(180.0, (1.0, 3.0), (5.0, 8.0), 1.0, 1.1, 1.1),
+            ],
+            normal: vec![
+                point(0.0, (1.0, 4.0), (5.0, 10.0), 1.0, 1.0, 1.0),
+                point(60.0, (0.8, 3.0), (4.0, 8.0), 0.9, 1.15, 1.15),
+                point(180.0, (0.5, 1.5), (2.0, 4.0), 0.6, 1.5, 1.6),
+            ],
+            hard: vec![
+                point(0.0, (0.8, 2.5), (3.0, 6.0), 0.8, 1.2, 1.2),
+                point(90.0, (0.4, 1.2), (2.0, 3.5), 0.5, 1.6, 1.7),
+                point(180.0, (0.25, 0.8), (1.0, 2.0), 0.35, 2.0, 2.2),
+            ],
+        }
+    }
+}
+
+impl DifficultyCurves {
+    pub fn curve(&self, difficulty: Difficulty) -> &[CurvePoint] {
+        match difficulty {
+            Difficulty::Easy => &self.easy,
+            Difficulty::Normal => &self.normal,
+            Difficulty::Hard => &self.hard,
+        }
+    }
+
+    pub fn validate(&self) -> Result<(), String> {
+        for (name, curve) in [("easy", &self.easy), ("normal", &self.normal), ("hard", &self.hard)].iter() {
+            if curve.is_empty() {
+                return Err(format!("{} curve has no points", name));
+            }
+            for pair in curve.windows(2) {
+                if pair[1].time < pair[0].time {
+                    return Err(format!("{} curve points must be sorted by time", name));
+                }
+            }
+            for p in curve.iter() {
+                let positive = [p.junk_interval.0, p.ship_interval.0, p.shot_interval, p.junk_speed, p.ship_speed];
+                if positive.iter().any(|v| !(*v > 0.0))
+                    || p.junk_interval.1 < p.junk_interval.0 || p.ship_interval.1 < p.ship_interval.0 {
+                    return Err(format!("{} curve has an invalid point at {}s", name, p.time));
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+/// The curve evaluated at the current session time.
+pub struct DifficultyLevel(pub CurvePoint);
+
+impl DifficultyLevel {
+    pub fn at(curve: &[CurvePoint], time: f32) -> CurvePoint {
+        let after = curve.iter().position(|p| p.time > time);
+        match after {
+            Some(0) => curve[0],
+            None => curve[curve.len() - 1],
+            Some(i) => {
+                let (a, b) = (&curve[i - 1], &curve[i]);
+                let t = (time - a.time) / (b.time - a.time);
+                let lerp = |x: f32, y: f32| x + (y - x) * t;
+                CurvePoint {
+                    time,
+                    junk_interval: (lerp(a.junk_interval.0, b.junk_interval.0), lerp(a.junk_interval.1, b.junk_interval.1)),
+                    ship_interval: (lerp(a.ship_interval.0, b.ship_interval.0), lerp(a.ship_interval.1, b.ship_interval.1)),
+                    shot_interval: lerp(a.shot_interval, b.shot_interval),
+                    junk_speed: lerp(a.junk_speed, b.junk_speed),
+                    ship_speed: lerp(a.ship_speed, b.ship_speed),
+                }
+            }
+        }
+    }
+
+    pub fn update(timer: Res<MainTimer>, config: Res<GameConfig>, difficulty: Res<Difficulty>,
+        mut level: ResMut<DifficultyLevel>)
+    {
+        let curve = config.difficulty.curve(*difficulty);
+        level.0 = DifficultyLevel::at(curve, timer.0.elapsed_secs());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_close(a: f32, b: f32) {
+        assert!((a - b).abs() < 1e-4, "{} != {}", a, b);
+    }
+
+    #[test]
+    fn holds_before_the_first_and_after_the_last_point() {
+        let curves = DifficultyCurves::default();
+        let normal = &curves.normal;
+        assert_eq!(DifficultyLevel::at(normal, 0.0), normal[0]);
+        assert_eq!(DifficultyLevel::at(normal, -5.0), normal[0]);
+        assert_eq!(DifficultyLevel::at(normal, 180.0), normal[2]);
+        assert_eq!(DifficultyLevel::at(normal, 1000.0), normal[2]);
+    }
+
+    #[test]
+    fn goes_through_the_points() {
+        let curves = DifficultyCurves::default();
+        let point = DifficultyLevel::at(&curves.normal, 60.0);
+        assert_eq!(point, curves.normal[1]);
+    }
+
+    #[test]
+    fn interpolates_between_points() {
+        let curves = DifficultyCurves::default();
+        // halfway from 60 s to 180 s
+        let point = DifficultyLevel::at(&curves.normal, 120.0);
+        assert_close(point.time, 120.0);
+        assert_close(point.junk_interval.0, 0.65);
+        assert_close(point.junk_interval.1, 2.25);
+        assert_close(point.ship_interval.0, 3.0);
+        assert_close(point.ship_interval.1, 6.0);
+        assert_close(point.shot_interval, 0.75);
+        assert_close(point.junk_speed, 1.325);
+        assert_close(point.ship_speed, 1.375);
+    }
+
+    #[test]
+    fn single_point_curve() {
+        let curves = DifficultyCurves::default();
+        let curve = &curves.easy[..1];
+        assert_eq!(DifficultyLevel::at(curve, 0.0), curve[0]);
+        assert_eq!(DifficultyLevel::at(curve, 100.0), curve[0]);
+    }
+
+    #[test]
+    fn presets() {
+        let curves = DifficultyCurves::default();
+        assert!(curves.validate().is_ok());
+        assert_eq!(curves.curve(Difficulty::Easy), &curves.easy[..]);
+        assert_eq!(curves.curve(Difficulty::Normal), &curves.normal[..]);
+        assert_eq!(curves.curve(Difficulty::Hard), &curves.hard[..]);
+        assert_eq!(DifficultyLevel::at(&curves.normal, 0.0).junk_speed, 1.0);
+
+        // every preset is harder than the one before it all the time: things come sooner and faster
+        let presets = [Difficulty::Easy, Difficulty::Normal, Difficulty::Hard];
+        for time in [0.0, 30.0, 60.0, 90.0, 120.0, 180.0, 300.0].iter() {
+            for pair in presets.windows(2) {
+                let easier = DifficultyLevel::at(curves.curve(pair[0]), *time);
+                let harder = DifficultyLevel::at(curves.curve(pair[1]), *time);
+                assert!(harder.junk_interval.0 < easier.junk_interval.0, "{:?} at {}", pair, time);
+                assert!(harder.junk_interval.1 < easier.junk_interval.1, "{:?} at {}", pair, time);
+                assert!(harder.ship_interval.0 < easier.ship_interval.0, "{:?} at {}", pair, time);
+                assert!(harder.ship_interval.1 < easier.ship_interval.1, "{:?} at {}", pair, time);
+                assert!(harder.shot_interval < easier.shot_interval, "{:?} at {}", pair, time);
+                assert!(harder.junk_speed > easier.junk_speed, "{:?} at {}", pair, time);
+                assert!(harder.ship_speed > easier.ship_speed, "{:?} at {}", pair, time);
+            }
+        }
+    }
+
+    #[test]
+    fn parses_presets() {
+        assert_eq!("easy".parse(), Ok(Difficulty::Easy));
+        assert_eq!("Normal".parse(), Ok(Difficulty::Normal));
+        assert_eq!("HARD".parse(), Ok(Difficulty::Hard));
+        assert_eq!("insane".parse::<Difficulty>(), Err(()));
+    }
+}
@@ -1,10 +1,14 @@
-use crate::{Params, TIME_STEP, Ball, Scoreboard, Particles, Collider};
+use crate::{Params, TIME_STEP, Ball, Collider, GameTime, GameRng, DifficultyLevel,
+    GameEvent, CurvePoint, GameConfig, Cannon, EnemyShip, AppState, SessionEntity};
 use bevy::prelude::*;
-use bevy::sprite::collide_aabb::collide;
-use rand::Rng;
+use rand::{rngs::StdRng, Rng};
+use std::collections::HashSet;
 
 pub struct EnemyTimer;
 
+/// Square units of junk per unit of mass.
+const AREA_PER_MASS: f32 = 1000.0;
+
 pub struct Enemies;
 
 #[derive (PartialEq, Eq)]
@@ -13,14 +17,56 @@ pub enum Direction {
     Right,
 }
 
+#[derive(Clone)]
 pub struct Enemy {
     speed: Vec2,
     angle: f32,
+    /// How many times this piece broke off bigger junk; 0 for junk that came in on its own.
+    pub generation: u32,
+}
+
+impl Enemy {
+    /// Units per second it drifts.
+    pub fn velocity(&self) -> Vec2 {
+        self.speed
+    }
+
+    /// Mass of junk of this size; bigger junk is harder to push around.
+    pub fn mass(size: Vec2) -> f32 {
+        size.x * size.y / AREA_PER_MASS
+    }
+
+    /// Junk is round enough: a circle a bit inside the sprite, like the hitbox the ship uses.
+    fn radius(size: Vec2) -> f32 {
+        size.x.min(size.y) * 0.5 * Cannon::hitbox(Collider::Spacejunk)
+    }
+
+    /// Changes the speed of junk of `size` by an `impulse`.
+    pub fn push(&mut self, impulse: Vec2, size: Vec2) {
+        self.speed += impulse / Enemy::mass(size);
+    }
+
+    /// Whether shot junk of `size` breaks into pieces; `spacejunk` is the size junk comes in at.
+    pub fn splits(size: Vec2, spacejunk: Vec2, config: &GameConfig) -> bool {
+        size.x >= spacejunk.x * config.junk_split_size
+    }
+
+    /// What shooting it is worth: broken off pieces are smaller and harder to hit, and score more.
+    pub fn score(&self, score_per_kill: usize) -> usize {
+        score_per_kill * (self.generation as usize + 1)
+    }
 }
 
 impl Enemies {
+    pub fn setup(mut commands: Commands) {
+        commands.spawn().insert(Timer::from_seconds(1.0, false))
+            .insert(EnemyTimer)
+            .insert(SessionEntity);
+    }
+
     pub fn spawner(mut commands: Commands, mut materials: ResMut<Assets<ColorMaterial>>, 
-        params: Res<Params>, mut query_timer: Query<&mut Timer, With<EnemyTimer>>, time: Res<Time>)
+        params: Res<Params>, mut query_timer: Query<&mut Timer, With<EnemyTimer>>, time: Res<GameTime>,
+        mut rng: ResMut<GameRng>, level: Res<DifficultyLevel>, mut events: EventWriter<GameEvent>)
     {
         let mut can_spawn = false;
         let mut timer = query_timer.single_mut().unwrap();
@@ -29,55 +75,178 @@ impl Enemies {
         } 
 
         if can_spawn {
-            let mut rng = rand::thread_rng();
-            let direction = 
-            if rng.gen_range(0.0..1.0) > 0.5 {
-                Direction::Left
-            } else {
-                Direction::Right
-            };
-            let x =
-            if direction == Direction::Left {
-                params.background.x * 0.5 + params.cannon.x * 0.5
-            } else {
-                -params.background.x * 0.5 - params.cannon.x * 0.5
-            };
-            let ymax = params.background.y * 0.5 - params.cannon.y * 0.5;
-            let ymin = -1.0 * ymax;            
-            let y = rng.gen_range(ymin..ymax);
-            let speedy = rng.gen_range(-100..100);
-            let speedx = 
-            if direction == Direction::Left {
-                rng.gen_range(-300..-100)
-            } else {
-                rng.gen_range(100..300)
-            };
-            let size = params.spacejunk.to_owned() * rng.gen_range(0.3..0.5);
-            let angle = rng.gen_range(-100..100);
-            commands.spawn_bundle(SpriteBundle {
-                material: materials.add(params.spacejunk_img.clone().into()),
-                transform: Transform::from_xyz(x, y, 1.0),
-                sprite: Sprite::new(size),
-                ..Default::default()
-            })
-            .insert(Enemy {speed: Vec2::new(speedx as f32, speedy as f32), angle: angle as f32})
-            .insert(Collider::Spacejunk);
+            let rng = &mut rng.0;
+            Enemies::spawn(&mut commands, &mut materials, &params, rng, &level.0, None);
+            events.send(GameEvent::Spawned(Collider::Spacejunk));
             
-            let interval = rng.gen_range(1..4); 
-            timer.set_duration(std::time::Duration::from_secs(interval));
+            let (min, max) = level.0.junk_interval;
+            let interval = if max > min { rng.gen_range(min..max) } else { min };
+            timer.set_duration(std::time::Duration::from_secs_f32(interval));
             timer.reset();
         }            
     }
+
+    /// Spawns a piece of junk drifting in from a random side, or at `pos` when given.
+    pub fn spawn(commands: &mut Commands, materials: &mut Assets<ColorMaterial>, params: &Params,
+        rng: &mut StdRng, level: &CurvePoint, pos: Option<Vec2>)
+    {
+        let direction = 
+        if rng.gen_range(0.0..1.0) > 0.5 {
+            Direction::Left
+        } else {
+            Direction::Right
+        };
+        let x =
+        if direction == Direction::Left {
+            params.background.x * 0.5 + params.cannon.x * 0.5
+        } else {
+            -params.background.x * 0.5 - params.cannon.x * 0.5
+        };
+        let ymax = params.background.y * 0.5 - params.cannon.y * 0.5;
+        let ymin = -ymax;
+        let y = rng.gen_range(ymin..ymax);
+        let pos = pos.unwrap_or_else(|| Vec2::new(x, y));
+        let speedy = rng.gen_range(-100..100) as f32 * level.junk_speed;
+        let speedx = 
+        if direction == Direction::Left {
+            rng.gen_range(-300..-100) as f32 * level.junk_speed
+        } else {
+            rng.gen_range(100..300) as f32 * level.junk_speed
+        };
+        let size = params.spacejunk.to_owned() * rng.gen_range(0.3..0.5);
+        let angle = rng.gen_range(-100..100);
+        Enemies::spawn_piece(commands, materials, params, pos, size,
+            Enemy {speed: Vec2::new(speedx, speedy), angle: angle as f32, generation: 0});
+    }
+
+    fn spawn_piece(commands: &mut Commands, materials: &mut Assets<ColorMaterial>, params: &Params,
+        pos: Vec2, size: Vec2, enemy: Enemy)
+    {
+        commands.spawn_bundle(SpriteBundle {
+            material: materials.add(params.spacejunk_img.clone().into()),
+            transform: Transform::from_xyz(pos.x, pos.y, 1.0),
+            sprite: Sprite::new(size),
+            ..Default::default()
+        })
+        .insert(enemy)
+        .insert(Collider::Spacejunk);
+    }
+
+    /// Breaks shot junk into smaller, faster pieces flying apart, like asteroids.
+    /// Junk already smaller than `junk_split_size` just goes. Returns the number of pieces.
+    pub fn split(commands: &mut Commands, materials: &mut Assets<ColorMaterial>, params: &Params,
+        rng: &mut StdRng, config: &GameConfig, pos: Vec2, size: Vec2, enemy: &Enemy) -> usize
+    {
+        if !Enemy::splits(size, params.spacejunk, config) {
+            return 0;
+        }
+        let (min, max) = config.junk_pieces;
+        let count = rng.gen_range(min..=max);
+        let piece = size * config.junk_piece_scale;
+        let drift = enemy.speed.length().max(100.0);
+        for i in 0..count {
+            // evenly around, give or take, on top of where the junk was going
+            let angle = std::f32::consts::PI * 2.0 * (i as f32 + rng.gen_range(-0.3..0.3)) / count as f32;
+            let away = Vec2::new(angle.cos(), angle.sin());
+            Enemies::spawn_piece(commands, materials, params, pos + away * piece.x * 0.5, piece, Enemy {
+                speed: (enemy.speed + away * drift) * config.junk_split_speed,
+                angle: rng.gen_range(-300.0..300.0),
+                generation: enemy.generation + 1,
+            });
+        }
+        count
+    }
     
-    pub fn update(mut commands: Commands, mut query: Query<(Entity, &mut Enemy, &Sprite, &mut Transform)>, params: Res<Params>){
-    
-        
-        for (entity, mut enemy, sprite,mut transform) in query.iter_mut() {
+    /// Rigid body physics for junk: pieces bounce off each other elastically and off enemy ships
+    /// and the players' ships, which are too heavy to be moved; enemy shots hitting junk push it
+    /// and are stopped.
+    pub fn physics(mut commands: Commands, mut junk: Query<(Entity, &mut Enemy, &Sprite, &mut Transform)>,
+        obstacles: Query<(&Sprite, &Transform, Option<&Cannon>, Option<&EnemyShip>),
+            (Or<(With<Cannon>, With<EnemyShip>)>, Without<Enemy>)>,
+        shots: Query<(Entity, &Ball, &Sprite, &Transform, &Collider), Without<Enemy>>, config: Res<GameConfig>)
+    {
+        // (entity, position, speed, radius, mass)
+        let mut bodies: Vec<_> = junk.iter_mut()
+            .map(|(entity, enemy, sprite, transform)| (entity, transform.translation.truncate(), enemy.speed,
+                Enemy::radius(sprite.size), Enemy::mass(sprite.size)))
+            .collect();
+        // enemy balls already stopped by a piece this frame
+        let mut spent = HashSet::new();
+
+        for i in 0..bodies.len() {
+            for j in i + 1..bodies.len() {
+                let (_, pos_a, speed_a, radius_a, mass_a) = bodies[i];
+                let (_, pos_b, speed_b, radius_b, mass_b) = bodies[j];
+                let offset = pos_b - pos_a;
+                let overlap = radius_a + radius_b - offset.length();
+                if overlap <= 0.0 {
+                    continue;
+                }
+                let normal = offset.normalize_or_zero();
+                let normal = if normal == Vec2::ZERO { Vec2::X } else { normal };
+                // equal and opposite impulses along the line between the centers
+                let approach = (speed_a - speed_b).dot(normal);
+                if approach > 0.0 {
+                    let impulse = normal * 2.0 * approach / (1.0 / mass_a + 1.0 / mass_b);
+                    bodies[i].2 -= impulse / mass_a;
+                    bodies[j].2 += impulse / mass_b;
+                }
+                // pull them apart so they don't stick, the lighter one moving more
+                let apart = normal * overlap / (mass_a + mass_b);
+                bodies[i].1 -= apart * mass_b;
+                bodies[j].1 += apart * mass_a;
+            }
+        }
+
+        for body in bodies.iter_mut() {
+            let (_, pos, speed, radius, mass) = body;
+            for (sprite, transform, cannon, enemyship) in obstacles.iter() {
+                let velocity = match (cannon, enemyship) {
+                    (Some(cannon), _) => cannon.velocity,
+                    (None, Some(enemyship)) => enemyship.velocity(),
+                    (None, None) => continue,
+                };
+                let offset = *pos - transform.translation.truncate();
+                let overlap = *radius + sprite.size.x.min(sprite.size.y) * 0.5 * Cannon::HITBOX - offset.length();
+                if overlap <= 0.0 {
+                    continue;
+                }
+                let normal = offset.normalize_or_zero();
+                let approach = (velocity - *speed).dot(normal);
+                if approach > 0.0 {
+                    *speed += normal * 2.0 * approach;
+                }
+                *pos += normal * overlap;
+            }
+            for (entity, ball, sprite, transform, collider) in shots.iter() {
+                if *collider != Collider::Enemyball || spent.contains(&entity) {
+                    continue;
+                }
+                let offset = *pos - transform.translation.truncate();
+                if offset.length() < *radius + sprite.size.x.min(sprite.size.y) * 0.5 {
+                    spent.insert(entity);
+                    *speed += ball.speed * config.shot_mass / *mass;
+                    commands.entity(entity).despawn();
+                }
+            }
+        }
+
+        for (entity, pos, speed, _, _) in bodies {
+            if let Ok((_, mut enemy, _, mut transform)) = junk.get_mut(entity) {
+                enemy.speed = speed;
+                transform.translation.x = pos.x;
+                transform.translation.y = pos.y;
+            }
+        }
+    }
+
+    pub fn update(mut query: Query<(&Enemy, &Sprite, &mut Transform)>, params: Res<Params>) {
+        for (enemy, sprite, mut transform) in query.iter_mut() {
             transform.translation.y += enemy.speed.y * TIME_STEP;
             transform.translation.x += enemy.speed.x * TIME_STEP;
 
             let maxy = params.background.y * 0.5 + sprite.size.y * 0.5;
-            let miny = maxy * -1.0;
+            let miny = -maxy;
 
             if transform.translation.y < miny {
                 transform.translation.y = maxy;
@@ -86,9 +255,53 @@ impl Enemies {
             }
 
             transform.rotate(Quat::from_rotation_z((enemy.angle * TIME_STEP).to_radians()));
-
-            let maxx = params.background.x * 0.5 + sprite.size.x * 0.5;
-            let minx = -maxx;
         }
     } 
-}
\ No newline at end of file
+}
+
+/// Space junk: spawning and drifting. `Enemies::physics` is up to the app, as the junk bounces off
+/// the ships and shots of the other plugins; `ShootingPlugin` adds it.
+pub struct EnemiesPlugin;
+
+impl Plugin for EnemiesPlugin {
+    fn build(&self, app: &mut AppBuilder) {
+        app
+            .add_system_set(SystemSet::on_enter(AppState::InGame)
+                .with_system(Enemies::setup.system())
+            )
+            .add_system_set(SystemSet::on_update(AppState::InGame)
+                .with_system(Enemies::update.system())
+                .with_system(Enemies::spawner.system())
+            );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn piece(generation: u32) -> Enemy {
+        Enemy { speed: Vec2::ZERO, angle: 0.0, generation }
+    }
+
+    #[test]
+    fn small_junk_does_not_split() {
+        let config = GameConfig { junk_split_size: 0.25, ..Default::default() };
+        let spacejunk = Vec2::new(100.0, 80.0);
+        assert!(Enemy::splits(spacejunk, spacejunk, &config));
+        assert!(Enemy::splits(Vec2::new(25.0, 20.0), spacejunk, &config));
+        assert!(!Enemy::splits(Vec2::new(24.9, 20.0), spacejunk, &config));
+
+        // 0 splits all the way down
+        let config = GameConfig { junk_split_size: 0.0, ..Default::default() };
+        assert!(Enemy::splits(Vec2::new(0.1, 0.1), spacejunk, &config));
+    }
+
+    #[test]
+    fn pieces_score_more_with_each_split() {
+        assert_eq!(piece(0).score(10), 10);
+        assert_eq!(piece(1).score(10), 20);
+        assert_eq!(piece(2).score(10), 30);
+        assert_eq!(piece(3).score(0), 0);
+    }
+}
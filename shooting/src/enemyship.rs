@@ -1,6 +1,6 @@
-use crate::{Params, TIME_STEP, Balls, Scoreboard, Particles, Cannon, Collider};
+use crate::{Params, TIME_STEP, Cannon, Collider, GameTime, GameRng, GameConfig,
+    DifficultyLevel, GameEvent, Aim, AppState, SessionEntity, Patterns};
 use bevy::prelude::*;
-use bevy::sprite::collide_aabb::collide;
 use rand::Rng;
 
 pub struct EnemyShipTimer;
@@ -11,13 +11,35 @@ pub struct EnemyShips;
 pub struct EnemyShip {
     speed: f32,
     direction: Vec2,
-    special: bool
+    /// Name of the bullet pattern it fires.
+    pattern: String,
+    /// Volleys fired so far, for patterns that turn.
+    volleys: u32,
+    aim: Aim,
+}
+
+impl EnemyShip {
+    /// Units per second it flies.
+    pub fn velocity(&self) -> Vec2 {
+        self.direction * self.speed
+    }
 }
 
 impl EnemyShips {
+    pub fn setup(mut commands: Commands) {
+        commands.spawn().insert(Timer::from_seconds(5.0, false))
+            .insert(EnemyShipTimer)
+            .insert(SessionEntity);
+
+        commands.spawn().insert(Timer::from_seconds(1.0, true))
+            .insert(EnemyShotTimer)
+            .insert(SessionEntity);
+    }
+
     pub fn spawner(mut commands: Commands, mut materials: ResMut<Assets<ColorMaterial>>,
         params: Res<Params>, mut query_timer: Query<&mut Timer, With<EnemyShipTimer>>, 
-        time: Res<Time>, query_cannon: Query<&Transform, With<Cannon>>)
+        time: Res<GameTime>, query_cannon: Query<&Transform, With<Cannon>>, mut rng: ResMut<GameRng>,
+        config: Res<GameConfig>, level: Res<DifficultyLevel>, mut events: EventWriter<GameEvent>)
     {
         let mut can_spawn = false;
         let mut timer = query_timer.single_mut().unwrap();
@@ -29,7 +51,7 @@ impl EnemyShips {
             return;
         }
 
-        let mut rng = rand::thread_rng();
+        let rng = &mut rng.0;
         let random = rng.gen_range(0.0..1.0) as f32;
         let maxx = params.background.x * 0.5;
         let maxy = params.background.y * 0.5;
@@ -47,10 +69,36 @@ impl EnemyShips {
             };
         
         let random = rng.gen_range(0.0..1.0) as f32;
-        let special = random < 0.5;
-        let cannon_transform = query_cannon.single().unwrap();
-        let cannon_pos = Vec2::new(cannon_transform.translation.x, cannon_transform.translation.y);
-        let direction = (cannon_pos - pos).normalize();
+        let (pattern, aim) = if random < 0.5 {
+            (&config.special_pattern, config.special_aim)
+        } else {
+            (&config.enemyship_pattern, config.enemyship_aim)
+        };
+        // head for the nearest player
+        let cannon_pos = query_cannon.iter()
+            .map(|transform| Vec2::new(transform.translation.x, transform.translation.y))
+            .min_by(|a, b| a.distance(pos).partial_cmp(&b.distance(pos)).unwrap());
+        let cannon_pos = match cannon_pos {
+            Some(cannon_pos) => cannon_pos,
+            None => return,
+        };
+        EnemyShips::spawn(&mut commands, &mut materials, &params, pos, cannon_pos,
+            config.enemyship_speed * level.0.ship_speed, pattern.clone(), aim);
+        events.send(GameEvent::Spawned(Collider::Enemyship));
+            
+            let (min, max) = level.0.ship_interval;
+            let interval = if max > min { rng.gen_range(min..max) } else { min };
+            timer.set_duration(std::time::Duration::from_secs_f32(interval));
+            timer.reset();                    
+    }
+    
+    /// Spawns a ship at `pos` flying towards `target`, firing the named bullet pattern.
+    pub fn spawn(commands: &mut Commands, materials: &mut Assets<ColorMaterial>, params: &Params,
+        pos: Vec2, target: Vec2, speed: f32, pattern: String, aim: Aim)
+    {
+        // one spawned right on its target still has to fly somewhere, not sit there
+        let offset = target - pos;
+        let direction = if offset.length_squared() > 0.0 { offset.normalize() } else { -Vec2::X };
         let radian = direction.y.atan2(direction.x) + std::f32::consts::PI;
         let size = params.enemyship;
         commands.spawn_bundle(SpriteBundle {
@@ -63,45 +111,58 @@ impl EnemyShips {
                 sprite: Sprite::new(size),
                 ..Default::default()
             })
-            .insert(EnemyShip {speed: 100.0, direction, special})
+            .insert(EnemyShip {speed, direction, pattern, volleys: 0, aim})
             .insert(Collider::Enemyship);
-            
-            let interval = rng.gen_range(5..10); 
-            timer.set_duration(std::time::Duration::from_secs(interval));
-            timer.reset();                    
     }
     
-    pub fn update(mut commands: Commands, mut query: Query<(Entity, &EnemyShip, &mut Transform)>, 
-        params: Res<Params>)
-    {  
-        for (entity, enemy, mut transform) in query.iter_mut() {
+    pub fn update(mut query: Query<(&EnemyShip, &mut Transform)>) {
+        for (enemy, mut transform) in query.iter_mut() {
             transform.translation.y += enemy.direction.y * enemy.speed * TIME_STEP;
             transform.translation.x += enemy.direction.x * enemy.speed * TIME_STEP;
         }
     } 
     
-    pub fn shoot(mut commands: Commands, query: Query<(&Transform, &EnemyShip), 
-        With<EnemyShip>>, params: Res<Params>,time: Res<Time>,
-        mut query_timer: Query<&mut Timer, With<EnemyShotTimer>>)
+    pub fn shoot(mut commands: Commands, mut query: Query<(&Transform, &mut EnemyShip)>,
+        cannons: Query<(&Transform, &Cannon)>, params: Res<Params>, time: Res<GameTime>,
+        mut query_timer: Query<&mut Timer, With<EnemyShotTimer>>, config: Res<GameConfig>,
+        level: Res<DifficultyLevel>, mut rng: ResMut<GameRng>)
     {
         let mut timer = query_timer.single_mut().unwrap();
+        timer.set_duration(std::time::Duration::from_secs_f32(level.0.shot_interval));
         if !timer.tick(time.delta()).just_finished() {
             return;
         } 
-        for (transform, enemyship) in query.iter() {
+        for (transform, mut enemyship) in query.iter_mut() {
+            let pattern = match config.patterns.get(&enemyship.pattern) {
+                Some(pattern) => pattern,
+                None => continue,
+            };
             let pos = Vec2::new(transform.translation.x, transform.translation.y);
-            Balls::spawn(&mut commands, pos.to_owned(), enemyship.direction.to_owned(), &params);
+            let target = cannons.iter()
+                .map(|(transform, cannon)| (transform.translation.truncate(), cannon.velocity))
+                .min_by(|a, b| a.0.distance(pos).partial_cmp(&b.0.distance(pos)).unwrap());
+            let speed = config.enemy_ball_speed;
+            let heading = enemyship.aim.direction(&mut rng.0, pos, enemyship.direction, target, speed * pattern.speed);
+            pattern.fire(&mut commands, pos, heading, enemyship.volleys, speed, &params);
+            enemyship.volleys += 1;
+        }
+    }
+}
 
-            if enemyship.special {
-                let radian = enemyship.direction.y.atan2(enemyship.direction.x);
-                let angle1 = radian + std::f32::consts::PI * 0.25;
-                let direction1 = Vec2::new(angle1.cos(), angle1.sin());
-                Balls::spawn(&mut commands, pos.to_owned(), direction1, &params);
+/// Enemy ships and the bullet patterns they fire.
+pub struct EnemyShipsPlugin;
 
-                let angle2 = radian - std::f32::consts::PI * 0.25;
-                let direction2 = Vec2::new(angle2.cos(), angle2.sin());
-                Balls::spawn(&mut commands, pos, direction2, &params);
-            }
-        }
+impl Plugin for EnemyShipsPlugin {
+    fn build(&self, app: &mut AppBuilder) {
+        app
+            .add_system_set(SystemSet::on_enter(AppState::InGame)
+                .with_system(EnemyShips::setup.system())
+            )
+            .add_system_set(SystemSet::on_update(AppState::InGame)
+                .with_system(EnemyShips::spawner.system())
+                .with_system(EnemyShips::update.system())
+                .with_system(EnemyShips::shoot.system())
+                .with_system(Patterns::update.system())
+            );
     }
-}
\ No newline at end of file
+}
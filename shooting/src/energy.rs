@@ -1,32 +1,107 @@
 use bevy::prelude::*;
 use rand::Rng;
-use crate::{Collider, Params};
+use crate::{Collider, Params, GameRng, GameTime, GameConfig, Cannon, GameEvent, AppState, SessionEntity, PlayerCount};
 use bevy::sprite::collide_aabb::collide;
 pub struct Energy;
 
+pub struct EnergyTimer;
+
+pub struct EnergyLifetime(pub Timer);
+
 impl Energy {
-    pub fn setup(mut commands: Commands, params: Res<Params>) {
-        let mut rng = rand::thread_rng();
+    pub fn setup(mut commands: Commands, params: Res<Params>, mut rng: ResMut<GameRng>, config: Res<GameConfig>,
+        players: Res<PlayerCount>, mut events: EventWriter<GameEvent>)
+    {
+        commands.spawn().insert(Timer::from_seconds(config.energy_respawn.0, false))
+            .insert(EnergyTimer)
+            .insert(SessionEntity);
+
+        let rng = &mut rng.0;
+        // the ships aren't spawned yet, keep clear of where they will be
+        let blockers: Vec<Vec2> = (0..players.0)
+            .map(|player| Cannon::start(player, players.0, params.cannon))
+            .collect();
+        for _ in 0..params.num_of_energies.min(config.max_energies) {
+            if let Some(pos) = Energy::free_position(rng, &params, &blockers, config.energy_clearance) {
+                Energy::spawn(&mut commands, pos, &params, &config);
+                events.send(GameEvent::Spawned(Collider::Energy));
+            }
+        }
+    }
+
+    pub fn spawn(commands: &mut Commands, pos: Vec2, params: &Params, config: &GameConfig) {
+        commands.spawn_bundle(SpriteBundle {
+            material: params.energy_img.clone(),
+            transform: Transform::from_xyz(pos.x, pos.y, 0.0),
+            sprite: Sprite::new(params.energy),
+            ..Default::default()
+        })
+        .insert(EnergyLifetime(Timer::from_seconds(config.energy_lifetime, false)))
+        .insert(Collider::Energy);
+    }
+
+    /// Picks a random spot on the field at least `clearance` away from every blocker.
+    fn free_position<R: Rng>(rng: &mut R, params: &Params, blockers: &[Vec2], clearance: f32) -> Option<Vec2> {
         let bounds = params.background.to_owned() * 0.5 - params.energy.to_owned() * 0.5;
-        for i in 0..params.num_of_energies {
-            let x = rng.gen_range(-bounds.x..bounds.x);
-            let y = rng.gen_range(-bounds.y..bounds.y);
-            commands.spawn_bundle(SpriteBundle {
-                material: params.energy_img.clone(),
-                transform: Transform::from_xyz(x, y, 0.0),
-                sprite: Sprite::new(params.energy.clone()),
-                ..Default::default()
-            }).insert(Collider::Energy);
+        for _ in 0..10 {
+            let pos = Vec2::new(rng.gen_range(-bounds.x..bounds.x), rng.gen_range(-bounds.y..bounds.y));
+            if blockers.iter().all(|blocker| blocker.distance(pos) >= clearance) {
+                return Some(pos);
+            }
         }
+        None
     }
 
-    pub fn collision(mut commands: Commands, 
-        mut colliders: Query<(Entity, &Sprite, &Transform, &Collider)> 
-    ){
+    pub fn spawner(mut commands: Commands, params: Res<Params>, config: Res<GameConfig>,
+        mut rng: ResMut<GameRng>, time: Res<GameTime>, mut query_timer: Query<&mut Timer, With<EnergyTimer>>,
+        colliders: Query<(&Transform, &Collider)>, cannon: Query<&Transform, With<Cannon>>,
+        mut events: EventWriter<GameEvent>)
+    {
+        let mut timer = query_timer.single_mut().unwrap();
+        if !timer.tick(time.delta()).finished() {
+            return;
+        }
+        let rng = &mut rng.0;
+        let (min, max) = config.energy_respawn;
+        let interval = if max > min { rng.gen_range(min..max) } else { min };
+        timer.set_duration(std::time::Duration::from_secs_f32(interval));
+        timer.reset();
+
+        let on_field = colliders.iter().filter(|(_, collider)| **collider == Collider::Energy).count();
+        if on_field >= config.max_energies {
+            return;
+        }
+        let blockers: Vec<Vec2> = colliders.iter()
+            .filter(|(_, collider)| **collider == Collider::Spacejunk || **collider == Collider::Enemyship)
+            .map(|(transform, _)| transform)
+            .chain(cannon.iter())
+            .map(|transform| Vec2::new(transform.translation.x, transform.translation.y))
+            .collect();
+        if let Some(pos) = Energy::free_position(rng, &params, &blockers, config.energy_clearance) {
+            Energy::spawn(&mut commands, pos, &params, &config);
+            events.send(GameEvent::Spawned(Collider::Energy));
+        }
+    }
+
+    /// Pickups blink during their last `energy_blink` seconds, then disappear.
+    pub fn expire(mut commands: Commands, config: Res<GameConfig>, time: Res<GameTime>,
+        mut query: Query<(Entity, &mut EnergyLifetime, &mut Visible)>)
+    {
+        for (entity, mut lifetime, mut visible) in query.iter_mut() {
+            if lifetime.0.tick(time.delta()).finished() {
+                commands.entity(entity).despawn();
+                continue;
+            }
+            let left = lifetime.0.duration().as_secs_f32() - lifetime.0.elapsed_secs();
+            visible.is_visible = left > config.energy_blink || (left * 4.0).fract() < 0.5;
+        }
+    }
+
+    pub fn collision(mut commands: Commands, colliders: Query<(Entity, &Sprite, &Transform, &Collider)>) {
         let energies = colliders.iter()
             .filter(|(_,_,_,collider)| **collider == Collider::Energy);
 
-        for (entity, sprite, transform, _) in energies {
+        for (_, sprite, transform, _) in energies {
             let enemyballs = colliders.iter()
             .filter(|(_,_,_,collider)| **collider == Collider::Enemyball);
             for (entity2, sprite2, transform2, _) in enemyballs {
@@ -42,4 +117,21 @@ impl Energy {
             }
         }
     }
-}
\ No newline at end of file
+}
+
+/// Energy pickups that give back health.
+pub struct EnergyPlugin;
+
+impl Plugin for EnergyPlugin {
+    fn build(&self, app: &mut AppBuilder) {
+        app
+            .add_system_set(SystemSet::on_enter(AppState::InGame)
+                .with_system(Energy::setup.system())
+            )
+            .add_system_set(SystemSet::on_update(AppState::InGame)
+                .with_system(Energy::collision.system())
+                .with_system(Energy::spawner.system())
+                .with_system(Energy::expire.system())
+            );
+    }
+}
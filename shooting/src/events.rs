@@ -0,0 +1,13 @@
+use crate::Collider;
+
+/// Things that happen during a game, sent by the gameplay systems
+/// for whoever keeps track of the session.
+#[derive(Debug, Clone, Copy)]
+pub enum GameEvent {
+    ShotFired,
+    Hit,
+    Killed(Collider),
+    DamageTaken,
+    EnergyCollected,
+    Spawned(Collider),
+}
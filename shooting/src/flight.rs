@@ -0,0 +1,75 @@
+use serde::{Deserialize, Serialize};
+use std::str::FromStr;
+use crate::Difficulty;
+
+/// How a ship responds to the stick.
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Serialize, Deserialize)]
+pub enum FlightModel {
+    /// Moves at `cannon_speed` where it is pushed and stops when let go.
+    Arcade,
+    /// Turns its nose where it is pushed and thrusts that way; it keeps drifting when let go.
+    Inertia,
+}
+
+impl FromStr for FlightModel {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "arcade" => Ok(FlightModel::Arcade),
+            "inertia" => Ok(FlightModel::Inertia),
+            _ => Err(()),
+        }
+    }
+}
+
+/// The flight model of each difficulty, and how the inertia model handles.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Flight {
+    pub easy: FlightModel,
+    pub normal: FlightModel,
+    pub hard: FlightModel,
+    /// Units per second gained every second at full thrust.
+    pub thrust: f32,
+    /// Share of its speed the ship loses every second.
+    pub drag: f32,
+    pub max_speed: f32,
+    /// Degrees per second the nose turns.
+    pub turn_rate: f32,
+}
+
+impl Default for Flight {
+    fn default() -> Self {
+        Flight {
+            easy: FlightModel::Arcade,
+            normal: FlightModel::Arcade,
+            hard: FlightModel::Arcade,
+            thrust: 1500.0,
+            drag: 1.2,
+            max_speed: 700.0,
+            turn_rate: 540.0,
+        }
+    }
+}
+
+impl Flight {
+    pub fn model(&self, difficulty: Difficulty) -> FlightModel {
+        match difficulty {
+            Difficulty::Easy => self.easy,
+            Difficulty::Normal => self.normal,
+            Difficulty::Hard => self.hard,
+        }
+    }
+
+    pub fn validate(&self) -> Result<(), String> {
+        let values = [self.thrust, self.max_speed, self.turn_rate];
+        if values.iter().any(|value| !(*value > 0.0 && value.is_finite())) {
+            return Err("thrust, max_speed and turn_rate must be positive".to_string());
+        }
+        if !(self.drag >= 0.0) {
+            return Err("drag can't be negative".to_string());
+        }
+        Ok(())
+    }
+}
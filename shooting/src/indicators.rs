@@ -0,0 +1,161 @@
+use bevy::prelude::*;
+use crate::{Ball, Cannon, Collider, EnemyShip, MainCamera, Params, SessionEntity};
+
+/// How far in from the edge of the view the arrows sit, in pixels.
+const INSET: f32 = 30.0;
+/// Arrow length next to the edge and far away, in pixels.
+const NEAR_SIZE: f32 = 28.0;
+const FAR_SIZE: f32 = 12.0;
+/// World units beyond the edge at which an arrow is smallest.
+const FAR: f32 = 1500.0;
+/// Seconds before a ball reaches a ship under which it is drawn as dangerous.
+const DANGER: f32 = 1.5;
+/// Thickness of the arrow strokes, in pixels.
+const LINE: f32 = 4.0;
+
+/// One stroke of an edge arrow; the strokes are moved to this frame's arrows, not respawned.
+pub struct Indicator;
+
+pub struct IndicatorMaterials {
+    enemyship: Handle<ColorMaterial>,
+    ball: Handle<ColorMaterial>,
+    danger: Handle<ColorMaterial>,
+}
+
+impl FromWorld for IndicatorMaterials {
+    fn from_world(world: &mut World) -> Self {
+        let mut materials = world.get_resource_mut::<Assets<ColorMaterial>>().unwrap();
+        IndicatorMaterials {
+            enemyship: materials.add(Color::rgba(1.0, 0.3, 0.3, 0.9).into()),
+            ball: materials.add(Color::rgba(1.0, 0.8, 0.2, 0.7).into()),
+            danger: materials.add(Color::rgba(1.0, 0.1, 0.1, 1.0).into()),
+        }
+    }
+}
+
+/// Arrows on the edge of the view pointing at enemy ships and incoming enemy balls that are
+/// out of sight; bigger the closer they are, red for balls about to hit.
+pub struct Indicators;
+
+/// The two strokes of a chevron at `pos` pointing along `direction`, `size` long: where each goes and its size.
+fn arrow(pos: Vec2, direction: Vec2, size: f32, zoom: f32) -> [(Transform, Vec2); 2] {
+    let angle = direction.y.atan2(direction.x);
+    let stroke = |side: f32| {
+        // each stroke runs from the tip back and out to one side
+        let stroke = angle + side * 135f32.to_radians();
+        let along = Vec2::new(stroke.cos(), stroke.sin());
+        let center = pos + along * size * zoom * 0.5;
+        let transform = Transform {
+            translation: center.extend(15.0),
+            rotation: Quat::from_rotation_z(stroke),
+            ..Default::default()
+        };
+        (transform, Vec2::new(size * zoom, LINE * zoom))
+    };
+    [stroke(-1.0), stroke(1.0)]
+}
+
+impl Indicators {
+    pub fn update(mut commands: Commands, params: Res<Params>, materials: Res<IndicatorMaterials>,
+        mut old: Query<(Entity, &mut Transform, &mut Sprite, &mut Handle<ColorMaterial>), With<Indicator>>,
+        cameras: Query<&Transform, (With<MainCamera>, Without<Indicator>)>,
+        cannons: Query<&Transform, (With<Cannon>, Without<Indicator>)>,
+        enemyships: Query<&Transform, (With<EnemyShip>, Without<Indicator>)>,
+        balls: Query<(&Transform, &Ball, &Collider), Without<Indicator>>)
+    {
+        let arrows = Indicators::arrows(&params, &materials, &cameras, &cannons, &enemyships, &balls);
+
+        // the strokes of last frame go to the first arrows, new ones are spawned and the rest despawned
+        let mut old = old.iter_mut();
+        for (pos, direction, size, zoom, material) in arrows {
+            for (transform, stroke) in arrow(pos, direction, size, zoom).iter() {
+                match old.next() {
+                    Some((_, mut old_transform, mut sprite, mut handle)) => {
+                        *old_transform = *transform;
+                        sprite.size = *stroke;
+                        if *handle != material {
+                            *handle = material.clone();
+                        }
+                    }
+                    None => {
+                        commands.spawn_bundle(SpriteBundle {
+                            material: material.clone(),
+                            transform: *transform,
+                            sprite: Sprite::new(*stroke),
+                            ..Default::default()
+                        })
+                        .insert(Indicator)
+                        .insert(SessionEntity);
+                    }
+                }
+            }
+        }
+        for (entity, ..) in old {
+            commands.entity(entity).despawn();
+        }
+    }
+
+    /// Each arrow to draw: where on the edge, pointing where, how big, at which zoom, in which color.
+    fn arrows(params: &Params, materials: &IndicatorMaterials,
+        cameras: &Query<&Transform, (With<MainCamera>, Without<Indicator>)>,
+        cannons: &Query<&Transform, (With<Cannon>, Without<Indicator>)>,
+        enemyships: &Query<&Transform, (With<EnemyShip>, Without<Indicator>)>,
+        balls: &Query<(&Transform, &Ball, &Collider), Without<Indicator>>)
+        -> Vec<(Vec2, Vec2, f32, f32, Handle<ColorMaterial>)>
+    {
+        let mut arrows = Vec::new();
+        let camera = match cameras.iter().next() {
+            Some(camera) => camera,
+            None => return arrows,
+        };
+        let zoom = camera.scale.x;
+        let center = camera.translation.truncate();
+        let half = params.bounds * zoom * 0.5;
+        let edge = half - Vec2::splat(INSET * zoom);
+        if edge.x <= 0.0 || edge.y <= 0.0 {
+            return arrows;
+        }
+
+        // where on the edge, how big; `None` while it is in view
+        let place = |target: Vec2| -> Option<(Vec2, Vec2, f32)> {
+            let offset = target - center;
+            if offset.x.abs() <= half.x && offset.y.abs() <= half.y {
+                return None;
+            }
+            let scale = (edge.x / offset.x.abs()).min(edge.y / offset.y.abs());
+            let beyond = ((offset.x.abs() - half.x).max(offset.y.abs() - half.y) / FAR).min(1.0);
+            let size = NEAR_SIZE + (FAR_SIZE - NEAR_SIZE) * beyond;
+            Some((center + offset * scale, offset.normalize(), size))
+        };
+
+        for transform in enemyships.iter() {
+            if let Some((pos, direction, size)) = place(transform.translation.truncate()) {
+                arrows.push((pos, direction, size, zoom, materials.enemyship.clone()));
+            }
+        }
+
+        let ships: Vec<Vec2> = cannons.iter().map(|transform| transform.translation.truncate()).collect();
+        for (transform, ball, collider) in balls.iter() {
+            if *collider != Collider::Enemyball {
+                continue;
+            }
+            let pos = transform.translation.truncate();
+            // seconds until it is closest to a ship it is flying at
+            let incoming = ships.iter()
+                .filter_map(|ship| {
+                    let closing = ball.speed.dot((*ship - pos).normalize_or_zero());
+                    if closing > 0.0 { Some(ship.distance(pos) / closing) } else { None }
+                })
+                .fold(None, |soonest: Option<f32>, time| Some(soonest.map_or(time, |soonest| soonest.min(time))));
+            let time = match incoming {
+                Some(time) => time,
+                None => continue,
+            };
+            if let Some((pos, direction, size)) = place(pos) {
+                let material = if time < DANGER { materials.danger.clone() } else { materials.ball.clone() };
+                arrows.push((pos, direction, size * 0.8, zoom, material));
+            }
+        }
+        arrows
+    }
+}
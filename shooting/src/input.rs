@@ -0,0 +1,87 @@
+use bevy::prelude::*;
+use crate::{Cannon, CursorWorld};
+
+/// How far ahead of the ship player two aims, as there is no mouse to point with.
+const AIM_DISTANCE: f32 = 200.0;
+/// Stick deflection below which a gamepad stick counts as centered.
+const DEAD_ZONE: f32 = 0.25;
+
+/// What a player asks their cannon to do this frame, whatever the source:
+/// keyboard and mouse, a gamepad, or a recorded replay. Each cannon carries its own.
+#[derive(Default, Clone, Copy)]
+pub struct PlayerInput {
+    pub movement: Vec2,
+    pub fire: bool,
+    pub aim: Option<Vec2>,
+}
+
+fn arrows(keyboard_input: &Input<KeyCode>, up: KeyCode, down: KeyCode, left: KeyCode, right: KeyCode) -> Vec2 {
+    let mut movement = Vec2::ZERO;
+    if keyboard_input.pressed(up) {
+        movement.y += 1.0;
+    }
+    if keyboard_input.pressed(down) {
+        movement.y -= 1.0;
+    }
+    if keyboard_input.pressed(left) {
+        movement.x -= 1.0;
+    }
+    if keyboard_input.pressed(right) {
+        movement.x += 1.0;
+    }
+    movement
+}
+
+impl PlayerInput {
+    /// Arrow keys to move, the mouse to aim and shoot.
+    pub fn keyboard_and_mouse(keyboard_input: &Input<KeyCode>, mouse_input: &Input<MouseButton>,
+        cursor: &CursorWorld) -> PlayerInput
+    {
+        PlayerInput {
+            movement: arrows(keyboard_input, KeyCode::Up, KeyCode::Down, KeyCode::Left, KeyCode::Right),
+            fire: mouse_input.just_pressed(MouseButton::Left),
+            aim: cursor.0,
+        }
+    }
+
+    /// Player one uses the arrow keys and the mouse,
+    /// player two WASD and Space, or the first gamepad.
+    pub fn read_devices(keyboard_input: Res<Input<KeyCode>>, mouse_input: Res<Input<MouseButton>>,
+        gamepad_buttons: Res<Input<GamepadButton>>, gamepad_axes: Res<Axis<GamepadAxis>>,
+        cursor: Res<CursorWorld>, mut query: Query<(&Cannon, &Transform, &mut PlayerInput)>)
+    {
+        for (cannon, transform, mut input) in query.iter_mut() {
+            if cannon.player == 0 {
+                *input = PlayerInput::keyboard_and_mouse(&keyboard_input, &mouse_input, &cursor);
+                continue;
+            }
+
+            let gamepad = Gamepad(0);
+            let stick = |x, y| Vec2::new(
+                gamepad_axes.get(GamepadAxis(gamepad, x)).unwrap_or(0.0),
+                gamepad_axes.get(GamepadAxis(gamepad, y)).unwrap_or(0.0),
+            );
+            let left_stick = stick(GamepadAxisType::LeftStickX, GamepadAxisType::LeftStickY);
+            let right_stick = stick(GamepadAxisType::RightStickX, GamepadAxisType::RightStickY);
+
+            input.movement = if left_stick.length() > DEAD_ZONE {
+                left_stick
+            } else {
+                arrows(&keyboard_input, KeyCode::W, KeyCode::S, KeyCode::A, KeyCode::D)
+            };
+            input.fire = keyboard_input.just_pressed(KeyCode::Space)
+                || gamepad_buttons.just_pressed(GamepadButton(gamepad, GamepadButtonType::South))
+                || gamepad_buttons.just_pressed(GamepadButton(gamepad, GamepadButtonType::RightTrigger2));
+
+            // aim with the right stick, else where the ship is going, else where it faces
+            let facing = if right_stick.length() > DEAD_ZONE {
+                right_stick.normalize()
+            } else if input.movement != Vec2::ZERO {
+                input.movement.normalize()
+            } else {
+                cannon.nose()
+            };
+            input.aim = Some(transform.translation.truncate() + facing * AIM_DISTANCE);
+        }
+    }
+}
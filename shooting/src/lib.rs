@@ -0,0 +1,321 @@
+//! Space Shooter as a library. `ShootingPlugin` adds the whole game to a bevy app;
+//! the `shooting` binary is one such app, a launcher or a test harness can be another.
+
+// systems take what they need as parameters, and validation rejects NaN with `!(x > 0.0)` on purpose
+#![allow(clippy::too_many_arguments, clippy::type_complexity, clippy::neg_cmp_op_on_partial_ord)]
+
+mod cannon;
+mod balls;
+mod enemies;
+mod stages;
+mod particle;
+mod enemyship;
+mod energy;
+mod crosshair;
+mod resolution;
+mod cli;
+mod config;
+mod difficulty;
+mod input;
+mod replay;
+mod events;
+mod stats;
+mod summary;
+mod menu;
+mod settings;
+mod camera;
+mod net;
+mod achievements;
+mod telemetry;
+mod debug;
+mod console;
+mod reload;
+mod patterns;
+mod aim;
+mod flight;
+mod indicators;
+mod plugin;
+mod bot;
+
+use bevy::{
+    prelude::*,
+    app::AppExit,
+    window::WindowId,
+};
+
+pub use cannon::{Cannon, CannonPlugin};
+use balls::{Balls, Ball};
+pub use enemies::{Enemies, Enemy, EnemyTimer, EnemiesPlugin};
+pub use enemyship::{EnemyShip, EnemyShipTimer, EnemyShips, EnemyShipsPlugin, EnemyShotTimer};
+pub use energy::{Energy, EnergyTimer, EnergyPlugin};
+pub use stages::{AppState, MenusPlugin, setup_world};
+pub use particle::{Particles, Particle, ParticlesPlugin};
+use crosshair::{Crosshair, CursorWorld, MainCamera, cursor_world_system};
+use resolution::{Resolution, Hud};
+pub use cli::{CliError, Options, USAGE};
+pub use config::{ConfigError, GameConfig};
+pub use difficulty::{Difficulty, DifficultyCurves, DifficultyLevel, CurvePoint};
+use input::PlayerInput;
+pub use replay::{Replay, ReplayFile};
+pub use events::GameEvent;
+pub use stats::Stats;
+use summary::Summary;
+use menu::{Menu, MenuAction, MenuMaterials, MenuRoot, MenuSelection};
+pub use settings::{Settings, Setting, SETTINGS_FILE};
+use camera::GameCamera;
+pub use net::{Network, add_remote_state};
+pub use achievements::{Achievements, AchievementUnlocked, ACHIEVEMENTS_FILE, UNLOCKED_FILE};
+use telemetry::{Telemetry, TELEMETRY_DIR};
+use debug::{DebugMaterials, DebugOverlay};
+use console::Console;
+use reload::ConfigWatcher;
+pub use patterns::{BulletPattern, PatternKind};
+use patterns::Patterns;
+pub use aim::{Aim, AimMode};
+use aim::intercept;
+pub use flight::{Flight, FlightModel};
+use indicators::{Indicators, IndicatorMaterials};
+use bot::Autopilot;
+use rand::rngs::StdRng;
+use std::time::Duration;
+pub use plugin::{ShootingPlugin, ShootingSettings};
+
+pub struct MainTimer(Timer);
+
+pub const TIME_STEP: f32 = 1.0 / 60.0;
+
+/// Frame time used by gameplay timers. It is fixed to `TIME_STEP` when headless
+/// and comes from the recording during a replay, so sessions can be reproduced.
+pub struct GameTime {
+    delta: Duration,
+    fixed: bool,
+}
+
+impl GameTime {
+    pub fn delta(&self) -> Duration {
+        self.delta
+    }
+
+    pub fn set_delta(&mut self, delta: Duration) {
+        self.delta = delta;
+    }
+
+    fn update(time: Res<Time>, mut game_time: ResMut<GameTime>) {
+        game_time.delta = if game_time.fixed {
+            Duration::from_secs_f32(TIME_STEP)
+        } else {
+            time.delta()
+        };
+    }
+}
+
+pub struct GameRng(pub StdRng);
+
+pub struct Params {
+    pub background: Vec2,
+    pub bounds: Vec2,
+    pub cannon: Vec2,
+    pub wall: f32,
+    pub ball: Vec2,
+    pub spacejunk_img: Handle<Texture>,
+    pub spacejunk: Vec2,
+    pub enemyship_img: Handle<Texture>,
+    pub enemyship: Vec2,
+    pub ball_self_color: Handle<ColorMaterial>,
+    pub ball_enemy_color: Handle<ColorMaterial>,
+    pub energy_img: Handle<ColorMaterial>,
+    pub num_of_energies: usize,
+    pub energy: Vec2,
+}
+
+/// The space image behind the field.
+pub struct Background;
+
+/// Marks the entities that live for one game and are despawned when it is over.
+pub struct SessionEntity;
+
+#[derive(Clone, Copy)]
+pub struct PlayerScore {
+    pub score: usize,
+    pub health: usize,
+}
+
+/// Score and health of each player.
+pub struct Scoreboard {
+    pub players: Vec<PlayerScore>,
+}
+
+impl Scoreboard {
+    pub fn new(players: usize, health: usize) -> Self {
+        Scoreboard { players: vec![PlayerScore { score: 0, health }; players] }
+    }
+
+    pub fn score(&self) -> usize {
+        self.players.iter().map(|player| player.score).sum()
+    }
+
+    pub fn health(&self) -> usize {
+        self.players.iter().map(|player| player.health).sum()
+    }
+}
+
+/// Number of players in the next game: 1, or 2 for local co-op.
+pub struct PlayerCount(pub usize);
+
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum Collider {
+    Spacejunk,
+    Enemyship,
+    Enemyball,
+    Selfball,
+    Energy,
+}
+
+/// Stands in for the window winit would have created, so window-sized layout keeps working.
+fn headless_window(descriptor: Res<WindowDescriptor>, mut windows: ResMut<Windows>) {
+    windows.add(Window::new(WindowId::primary(), &descriptor,
+        descriptor.width as u32, descriptor.height as u32, 1.0, None));
+}
+
+pub fn setup_hud(mut commands: Commands, asset_server: Res<AssetServer>) {
+    commands.spawn_bundle(TextBundle {
+        text: Text {
+            sections: vec![
+                TextSection {
+                    value: "Score: ".to_string(),
+                    style: TextStyle {
+                        font: asset_server.load("fonts/FiraSans-Bold.ttf"),
+                        font_size: 40.0,
+                        color: Color::rgb(0.5, 0.5, 1.0),
+                    },
+                },
+                TextSection {
+                    value: "".to_string(),
+                    style: TextStyle {
+                        font: asset_server.load("fonts/FiraMono-Medium.ttf"),
+                        font_size: 40.0,
+                        color: Color::rgb(1.0, 0.5, 0.5),
+                    },
+                },
+                TextSection {
+                    value: "\nTime: ".to_string(),
+                    style: TextStyle {
+                        font: asset_server.load("fonts/FiraSans-Bold.ttf"),
+                        font_size: 40.0,
+                        color: Color::rgb(0.5, 0.5, 1.0),
+                    },
+                },
+                TextSection {
+                    value: "".to_string(),
+                    style: TextStyle {
+                        font: asset_server.load("fonts/FiraMono-Medium.ttf"),
+                        font_size: 40.0,
+                        color: Color::rgb(1.0, 0.5, 0.5),
+                    },
+                },
+                TextSection {
+                    value: "\nHealth: ".to_string(),
+                    style: TextStyle {
+                        font: asset_server.load("fonts/FiraSans-Bold.ttf"),
+                        font_size: 40.0,
+                        color: Color::rgb(0.5, 0.5, 1.0),
+                    },
+                },
+                TextSection {
+                    value: "".to_string(),
+                    style: TextStyle {
+                        font: asset_server.load("fonts/FiraMono-Medium.ttf"),
+                        font_size: 40.0,
+                        color: Color::rgb(1.0, 0.5, 0.5),
+                    },
+                },
+            ],
+            ..Default::default()
+        },
+        style: Style {
+            position_type: PositionType::Absolute,
+            position: Rect {
+                top: Val::Px(20.0),
+                left: Val::Px(20.0),
+                ..Default::default()
+            },
+            ..Default::default()
+        },
+        ..Default::default()
+    })
+    .insert(Hud)
+    .insert(SessionEntity);
+}
+
+pub fn scoreboard_system(scoreboard: Res<Scoreboard>, mut query: Query<&mut Text, With<Hud>>, timer: Res<MainTimer>,
+    config: Res<GameConfig>)
+{
+    let join = |value: fn(&PlayerScore) -> usize| scoreboard.players.iter()
+        .map(|player| value(player).to_string())
+        .collect::<Vec<_>>()
+        .join(" / ");
+    let mut text = query.single_mut().unwrap();
+    text.sections[1].value = join(|player| player.score);
+    text.sections[3].value = (config.duration - timer.0.elapsed_secs()).trunc().to_string();
+    text.sections[5].value = join(|player| player.health);
+}
+
+/// Every session starts at the stage given with `--stage`; each stage is an equal slice of the session.
+fn start_session(mut scoreboard: ResMut<Scoreboard>, mut timer: ResMut<MainTimer>, config: Res<GameConfig>,
+    options: Res<Options>, players: Res<PlayerCount>, mut level: ResMut<DifficultyLevel>)
+{
+    *scoreboard = Scoreboard::new(players.0, config.health);
+    timer.0.reset();
+    let stage = options.stage.unwrap_or(1);
+    let stage_secs = config.duration / Options::STAGES as f32;
+    timer.0.set_elapsed(Duration::from_secs_f32(stage_secs * (stage - 1) as f32));
+    // the first frame spawns at the level of the stage, not of the last game
+    level.0 = DifficultyLevel::at(config.difficulty.curve(options.difficulty), timer.0.elapsed_secs());
+}
+
+fn headless_exit(options: Res<Options>, scoreboard: Res<Scoreboard>, stats: Res<Stats>,
+    mut exit: EventWriter<AppExit>)
+{
+    if options.headless {
+        println!("score: {} health: {} time: {:.1} junk: {} ships: {} accuracy: {:.0}%",
+            scoreboard.score(), scoreboard.health(), stats.time_survived,
+            stats.junk_kills, stats.ship_kills, stats.accuracy());
+        exit.send(AppExit);
+    }
+}
+
+fn timer_system(time: Res<GameTime>, mut timer: ResMut<MainTimer>) {
+    timer.0.tick(time.delta());
+}
+
+/// Ends the game when the time is up or every player is out of health. Nothing else leaves a game,
+/// so the state can't already be queued.
+fn game_over(timer: Res<MainTimer>, scoreboard: Res<Scoreboard>, mut state: ResMut<State<AppState>>) {
+    if timer.0.finished() || scoreboard.players.iter().all(|player| player.health == 0) {
+        state.set(AppState::Finish).unwrap();
+    }
+}
+
+pub fn cleanup_session(mut commands: Commands,
+    query: Query<Entity, Or<(With<SessionEntity>, With<Collider>, With<Particle>)>>)
+{
+    for entity in query.iter() {
+        commands.entity(entity).despawn_recursive();
+    }
+}
+
+pub fn cleanup_boundaries(mut commands: Commands, 
+        params: Res<Params>, query: Query<(Entity, &Sprite, &Transform), With<Collider>>)
+{
+    for (entity, sprite, transform) in query.iter() {
+        let maxx = params.background.x * 0.5 + sprite.size.x;
+        let maxy = params.background.y * 0.5 + sprite.size.y;
+        let x = transform.translation.x;
+        let y = transform.translation.y;
+
+        if x < -maxx || x > maxx || y < -maxy || y > maxy {
+            commands.entity(entity).despawn();
+        }
+    }
+}
+
@@ -0,0 +1,161 @@
+use bevy::prelude::*;
+use crate::Setting;
+
+/// What a menu button does when it is clicked or chosen with Enter.
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum MenuAction {
+    Play,
+    PlayCoop,
+    Retry,
+    MainMenu,
+    Settings,
+    Change(Setting),
+    Achievements,
+    Back,
+    Quit,
+}
+
+/// Top node of a menu; despawning it removes the whole menu.
+pub struct MenuRoot;
+
+pub struct MenuButton {
+    pub action: MenuAction,
+    pub index: usize,
+}
+
+/// Index of the button selected with the keyboard or last hovered by the mouse.
+pub struct MenuSelection(pub usize);
+
+pub struct MenuMaterials {
+    pub root: Handle<ColorMaterial>,
+    pub normal: Handle<ColorMaterial>,
+    pub hovered: Handle<ColorMaterial>,
+    pub pressed: Handle<ColorMaterial>,
+    pub none: Handle<ColorMaterial>,
+}
+
+impl FromWorld for MenuMaterials {
+    fn from_world(world: &mut World) -> Self {
+        let mut materials = world.get_resource_mut::<Assets<ColorMaterial>>().unwrap();
+        MenuMaterials {
+            root: materials.add(Color::rgba(0.0, 0.0, 0.0, 0.6).into()),
+            normal: materials.add(Color::rgba(0.3, 0.3, 0.3, 0.5).into()),
+            hovered: materials.add(Color::rgba(0.4, 0.4, 0.6, 0.8).into()),
+            pressed: materials.add(Color::rgba(0.3, 0.6, 0.3, 0.9).into()),
+            none: materials.add(Color::NONE.into()),
+        }
+    }
+}
+
+pub struct Menu;
+
+impl Menu {
+    /// Spawns a full-screen menu: `header` adds the nodes above the buttons,
+    /// then one button per `(text, action)`, top to bottom.
+    pub fn spawn(commands: &mut Commands, materials: &MenuMaterials, font: Handle<Font>,
+        buttons: &[(&str, MenuAction)], header: impl FnOnce(&mut ChildBuilder))
+    {
+        commands.insert_resource(MenuSelection(0));
+        commands
+            .spawn_bundle(NodeBundle {
+                style: Style {
+                    size: Size::new(Val::Percent(100.0), Val::Percent(100.0)),
+                    flex_direction: FlexDirection::ColumnReverse,
+                    justify_content: JustifyContent::Center,
+                    align_items: AlignItems::Center,
+                    ..Default::default()
+                },
+                material: materials.root.clone(),
+                ..Default::default()
+            })
+            .insert(MenuRoot)
+            .with_children(|parent| {
+                header(parent);
+                for (index, (text, action)) in buttons.iter().enumerate() {
+                    parent
+                        .spawn_bundle(ButtonBundle {
+                            style: Style {
+                                size: Size::new(Val::Px(250.0), Val::Px(65.0)),
+                                margin: Rect::all(Val::Px(8.0)),
+                                // horizontally center child text
+                                justify_content: JustifyContent::Center,
+                                // vertically center child text
+                                align_items: AlignItems::Center,
+                                ..Default::default()
+                            },
+                            material: materials.normal.clone(),
+                            ..Default::default()
+                        })
+                        .insert(MenuButton { action: *action, index })
+                        .with_children(|parent| {
+                            parent.spawn_bundle(TextBundle {
+                                text: Text::with_section(
+                                    *text,
+                                    TextStyle {
+                                        font: font.clone(),
+                                        font_size: 40.0,
+                                        color: Color::rgb(0.9, 0.9, 0.9),
+                                    },
+                                    Default::default(),
+                                ),
+                                ..Default::default()
+                            });
+                        });
+                }
+            });
+    }
+
+    /// Up/Down (or Left/Right) move the selection, Enter activates it; clicks activate the clicked button.
+    pub fn input(keyboard_input: Res<Input<KeyCode>>, mut selection: ResMut<MenuSelection>,
+        buttons: Query<(&MenuButton, &Interaction)>, changed: Query<(&MenuButton, &Interaction), Changed<Interaction>>,
+        mut actions: EventWriter<MenuAction>)
+    {
+        let count = buttons.iter().count();
+        if count == 0 {
+            return;
+        }
+        if keyboard_input.just_pressed(KeyCode::Up) || keyboard_input.just_pressed(KeyCode::Left) {
+            selection.0 = (selection.0 + count - 1) % count;
+        }
+        if keyboard_input.just_pressed(KeyCode::Down) || keyboard_input.just_pressed(KeyCode::Right) {
+            selection.0 = (selection.0 + 1) % count;
+        }
+        selection.0 = selection.0.min(count - 1);
+
+        for (button, interaction) in changed.iter() {
+            match *interaction {
+                Interaction::Clicked => actions.send(button.action),
+                Interaction::Hovered => selection.0 = button.index,
+                Interaction::None => {}
+            }
+        }
+        if keyboard_input.just_pressed(KeyCode::Return) {
+            if let Some((button, _)) = buttons.iter().find(|(button, _)| button.index == selection.0) {
+                actions.send(button.action);
+            }
+        }
+    }
+
+    pub fn highlight(selection: Res<MenuSelection>, materials: Res<MenuMaterials>,
+        mut buttons: Query<(&MenuButton, &Interaction, &mut Handle<ColorMaterial>)>)
+    {
+        for (button, interaction, mut material) in buttons.iter_mut() {
+            let wanted = if *interaction == Interaction::Clicked {
+                &materials.pressed
+            } else if button.index == selection.0 {
+                &materials.hovered
+            } else {
+                &materials.normal
+            };
+            if *material != *wanted {
+                *material = wanted.clone();
+            }
+        }
+    }
+
+    pub fn cleanup(mut commands: Commands, query: Query<Entity, With<MenuRoot>>) {
+        for entity in query.iter() {
+            commands.entity(entity).despawn_recursive();
+        }
+    }
+}
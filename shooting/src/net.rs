@@ -0,0 +1,630 @@
+use bevy::app::AppExit;
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::io;
+use std::net::{SocketAddr, UdpSocket};
+use crate::{AppState, Cannon, Collider, Crosshair, CursorWorld, GameCamera, MainTimer, Params, Particle, PlayerInput,
+    PlayerScore, Resolution, Scoreboard, SessionEntity, Stats, scoreboard_system, setup_hud};
+
+/// Seconds between two join requests while the host doesn't answer.
+const JOIN_INTERVAL: f32 = 0.5;
+/// Seconds without a message before the client gives up on the host, or the host on the client.
+const TIMEOUT: f32 = 10.0;
+/// The client sends its input at most this often, in seconds.
+const SEND_INTERVAL: f32 = 1.0 / 60.0;
+/// Times the final result is sent, as a datagram can be lost.
+const FINISH_REPEAT: usize = 3;
+/// Largest UDP payload; a snapshot drops its last sprites, explosion particles first, to fit.
+const MAX_DATAGRAM: usize = 65507;
+
+/// What the host draws this frame, and the scores, sent to the client after every frame.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct Snapshot {
+    pub elapsed: f32,
+    pub players: Vec<(usize, usize)>,
+    pub sprites: Vec<NetSprite>,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum NetKind {
+    Cannon(usize),
+    Spacejunk,
+    Enemyship,
+    Enemyball,
+    Selfball,
+    Energy,
+    Particle,
+}
+
+#[derive(Clone, Copy, Serialize, Deserialize)]
+pub struct NetSprite {
+    pub kind: NetKind,
+    pub translation: (f32, f32, f32),
+    pub rotation: (f32, f32, f32, f32),
+    pub scale: f32,
+    pub size: (f32, f32),
+    pub flip_x: bool,
+    pub flip_y: bool,
+    pub visible: bool,
+}
+
+#[derive(Clone, Copy, Serialize, Deserialize)]
+pub struct NetInput {
+    pub movement: (f32, f32),
+    pub fire: bool,
+    pub aim: Option<(f32, f32)>,
+}
+
+#[derive(Serialize, Deserialize)]
+pub enum NetMessage {
+    Join,
+    Welcome,
+    Input(NetInput),
+    Snapshot(Snapshot),
+    Finish { players: Vec<(usize, usize)>, stats: Stats },
+    /// The client quits; the host takes another one in.
+    Leave,
+}
+
+/// Networked co-op: the host runs the game and plays player one, the client plays player two.
+/// The client only sends its input and draws the snapshots it gets back.
+#[derive(Default)]
+pub enum Network {
+    #[default]
+    Off,
+    Host {
+        socket: UdpSocket,
+        peer: Option<SocketAddr>,
+        /// Latest input of the client; a shot counts until the host has used it.
+        remote: PlayerInput,
+        /// Seconds since the last message from the client.
+        silence: f32,
+    },
+    Client {
+        socket: UdpSocket,
+        host: SocketAddr,
+        connected: bool,
+        /// Seconds since the last message from the host.
+        silence: f32,
+        since_send: f32,
+        pending_fire: bool,
+        snapshot: Option<Snapshot>,
+        finish: Option<(Vec<(usize, usize)>, Stats)>,
+    },
+}
+
+/// Client side stand-in for an entity of the host, kept from one snapshot to the next.
+pub struct RemoteSprite(NetKind);
+
+pub struct RemoteMaterials {
+    cannons: Vec<Handle<ColorMaterial>>,
+    spacejunk: Handle<ColorMaterial>,
+    enemyship: Handle<ColorMaterial>,
+    particle: Handle<ColorMaterial>,
+}
+
+impl Network {
+    pub fn host(port: u16) -> io::Result<Network> {
+        let socket = UdpSocket::bind(("0.0.0.0", port))?;
+        socket.set_nonblocking(true)?;
+        Ok(Network::Host { socket, peer: None, remote: PlayerInput::default(), silence: 0.0 })
+    }
+
+    pub fn join(host: SocketAddr) -> io::Result<Network> {
+        let any: SocketAddr = if host.is_ipv4() { ([0, 0, 0, 0], 0).into() } else { ([0u16; 8], 0).into() };
+        let socket = UdpSocket::bind(any)?;
+        socket.set_nonblocking(true)?;
+        Ok(Network::Client {
+            socket,
+            host,
+            connected: false,
+            // ask right away
+            silence: JOIN_INTERVAL,
+            since_send: 0.0,
+            pending_fire: false,
+            snapshot: None,
+            finish: None,
+        })
+    }
+
+    pub fn is_client(&self) -> bool {
+        matches!(self, Network::Client { .. })
+    }
+
+    /// A host is ready to start once a client has joined.
+    pub fn ready(&self) -> bool {
+        match self {
+            Network::Host { peer, .. } => peer.is_some(),
+            _ => true,
+        }
+    }
+
+    /// The message as sent, within one datagram; a snapshot loses sprites from the end until it fits.
+    fn encode(mut message: NetMessage) -> Result<String, String> {
+        loop {
+            let text = ron::ser::to_string(&message).map_err(|err| err.to_string())?;
+            if text.len() <= MAX_DATAGRAM {
+                return Ok(text);
+            }
+            match &mut message {
+                NetMessage::Snapshot(snapshot) if !snapshot.sprites.is_empty() => {
+                    // about as many as fit, and at least one less
+                    let sprites = &mut snapshot.sprites;
+                    let keep = sprites.len() * MAX_DATAGRAM / text.len();
+                    sprites.truncate(keep.min(sprites.len() - 1));
+                }
+                _ => return Err(format!("{} bytes don't fit in a datagram", text.len())),
+            }
+        }
+    }
+
+    fn send(socket: &UdpSocket, to: SocketAddr, message: NetMessage) {
+        let result = Network::encode(message)
+            .and_then(|text| socket.send_to(text.as_bytes(), to).map(|_| ()).map_err(|err| err.to_string()));
+        if let Err(err) = result {
+            warn!("can't send to {}: {}", to, err);
+        }
+    }
+
+    /// Every datagram waiting on the socket; ones that don't parse are dropped.
+    fn receive_all(socket: &UdpSocket) -> Vec<(NetMessage, SocketAddr)> {
+        let mut messages = Vec::new();
+        let mut buffer = [0u8; 65536];
+        loop {
+            match socket.recv_from(&mut buffer) {
+                Ok((len, from)) => {
+                    let message = std::str::from_utf8(&buffer[..len]).ok()
+                        .and_then(|text| ron::de::from_str(text).ok());
+                    match message {
+                        Some(message) => messages.push((message, from)),
+                        None => warn!("dropping a bad message from {}", from),
+                    }
+                }
+                Err(err) if err.kind() == io::ErrorKind::WouldBlock => break,
+                // e.g. the peer's port is closed, reported on the next receive on some systems
+                Err(_) => break,
+            }
+        }
+        messages
+    }
+
+    /// Host: takes the client in, feeds its input to player two and lets it go when it leaves or goes silent.
+    /// Client: keeps the latest snapshot and the final result for `client_update`.
+    pub fn receive(mut network: ResMut<Network>, time: Res<Time>, mut inputs: Query<(&Cannon, &mut PlayerInput)>) {
+        match &mut *network {
+            Network::Off => {}
+            Network::Host { socket, peer, remote, silence } => {
+                *silence += time.delta_seconds();
+                for (message, from) in Network::receive_all(socket) {
+                    if *peer == Some(from) {
+                        *silence = 0.0;
+                    }
+                    match message {
+                        NetMessage::Join => {
+                            if peer.is_none() {
+                                info!("player two joined from {}", from);
+                                *peer = Some(from);
+                                *silence = 0.0;
+                            }
+                            if *peer == Some(from) {
+                                Network::send(socket, from, NetMessage::Welcome);
+                            }
+                        }
+                        NetMessage::Input(input) if *peer == Some(from) => {
+                            remote.movement = Vec2::new(input.movement.0, input.movement.1);
+                            remote.aim = input.aim.map(|(x, y)| Vec2::new(x, y));
+                            remote.fire |= input.fire;
+                        }
+                        NetMessage::Leave if *peer == Some(from) => {
+                            info!("player two left");
+                            *peer = None;
+                        }
+                        _ => {}
+                    }
+                }
+                if peer.is_some() && *silence > TIMEOUT {
+                    warn!("lost player two");
+                    *peer = None;
+                }
+                if peer.is_none() {
+                    *remote = PlayerInput::default();
+                }
+                if let Some((_, mut input)) = inputs.iter_mut().find(|(cannon, _)| cannon.player == 1) {
+                    *input = *remote;
+                    remote.fire = false;
+                }
+            }
+            Network::Client { socket, host, connected, silence, snapshot, finish, .. } => {
+                *silence += time.delta_seconds();
+                for (message, from) in Network::receive_all(socket) {
+                    if from != *host {
+                        continue;
+                    }
+                    *silence = 0.0;
+                    match message {
+                        NetMessage::Welcome => {
+                            if !*connected {
+                                info!("joined {}", host);
+                            }
+                            *connected = true;
+                        }
+                        NetMessage::Snapshot(new) => {
+                            *connected = true;
+                            *snapshot = Some(new);
+                        }
+                        NetMessage::Finish { players, stats } => *finish = Some((players, stats)),
+                        _ => {}
+                    }
+                }
+            }
+        }
+    }
+
+    /// Sends what is on the field to the client.
+    pub fn send_snapshot(network: Res<Network>, timer: Res<MainTimer>, scoreboard: Res<Scoreboard>,
+        sprites: Query<(&Transform, &Sprite, &Visible, Option<&Collider>, Option<&Cannon>),
+            Or<(With<Collider>, With<Cannon>, With<Particle>)>>)
+    {
+        let (socket, peer) = match &*network {
+            Network::Host { socket, peer: Some(peer), .. } => (socket, *peer),
+            _ => return,
+        };
+        let mut sprites: Vec<NetSprite> = sprites.iter().map(|(transform, sprite, visible, collider, cannon)| {
+            let kind = match (cannon, collider) {
+                (Some(cannon), _) => NetKind::Cannon(cannon.player),
+                (_, Some(Collider::Spacejunk)) => NetKind::Spacejunk,
+                (_, Some(Collider::Enemyship)) => NetKind::Enemyship,
+                (_, Some(Collider::Enemyball)) => NetKind::Enemyball,
+                (_, Some(Collider::Selfball)) => NetKind::Selfball,
+                (_, Some(Collider::Energy)) => NetKind::Energy,
+                (None, None) => NetKind::Particle,
+            };
+            let t = transform.translation;
+            let r: [f32; 4] = transform.rotation.into();
+            NetSprite {
+                kind,
+                translation: (t.x, t.y, t.z),
+                rotation: (r[0], r[1], r[2], r[3]),
+                scale: transform.scale.x,
+                size: (sprite.size.x, sprite.size.y),
+                flip_x: sprite.flip_x,
+                flip_y: sprite.flip_y,
+                visible: visible.is_visible,
+            }
+        }).collect();
+        sprites.sort_by_key(|sprite| matches!(sprite.kind, NetKind::Particle));
+        let snapshot = Snapshot {
+            elapsed: timer.0.elapsed_secs(),
+            players: scoreboard.players.iter().map(|player| (player.score, player.health)).collect(),
+            sprites,
+        };
+        Network::send(socket, peer, NetMessage::Snapshot(snapshot));
+    }
+
+    pub fn send_finish(network: Res<Network>, scoreboard: Res<Scoreboard>, stats: Res<Stats>) {
+        if let Network::Host { socket, peer: Some(peer), .. } = &*network {
+            let players: Vec<_> = scoreboard.players.iter().map(|player| (player.score, player.health)).collect();
+            for _ in 0..FINISH_REPEAT {
+                Network::send(socket, *peer, NetMessage::Finish { players: players.clone(), stats: stats.clone() });
+            }
+        }
+    }
+
+    pub fn setup_materials(mut commands: Commands, mut materials: ResMut<Assets<ColorMaterial>>,
+        asset_server: Res<AssetServer>, params: Res<Params>)
+    {
+        let rocket = asset_server.load("images/player-rocket.png");
+        commands.insert_resource(RemoteMaterials {
+            cannons: Cannon::TINTS.iter()
+                .map(|tint| materials.add(ColorMaterial::modulated_texture(rocket.clone(), *tint)))
+                .collect(),
+            spacejunk: materials.add(params.spacejunk_img.clone().into()),
+            enemyship: materials.add(params.enemyship_img.clone().into()),
+            particle: materials.add(asset_server.load("images/imgbin_explosion-sprite-png.png").into()),
+        });
+    }
+
+    /// Client: joins, sends the local input, mirrors the host's state and follows it into and out of games.
+    pub fn client_update(mut commands: Commands, mut network: ResMut<Network>, mut state: ResMut<State<AppState>>,
+        time: Res<Time>, keyboard_input: Res<Input<KeyCode>>, mouse_input: Res<Input<MouseButton>>,
+        cursor: Res<CursorWorld>, mut scoreboard: ResMut<Scoreboard>, mut stats: ResMut<Stats>,
+        mut timer: ResMut<MainTimer>, params: Res<Params>, materials: Option<Res<RemoteMaterials>>,
+        mut remote_sprites: Query<(Entity, &RemoteSprite, &mut Transform, &mut Sprite, &mut Visible)>)
+    {
+        let (socket, host, connected, silence, since_send, pending_fire, snapshot, finish) = match &mut *network {
+            Network::Client { socket, host, connected, silence, since_send, pending_fire, snapshot, finish } =>
+                (socket, *host, connected, silence, since_send, pending_fire, snapshot, finish),
+            _ => return,
+        };
+        let current = state.current().clone();
+
+        if !*connected {
+            if *silence >= JOIN_INTERVAL {
+                Network::send(socket, host, NetMessage::Join);
+                *silence = 0.0;
+            }
+            return;
+        }
+
+        let input = PlayerInput::keyboard_and_mouse(&keyboard_input, &mouse_input, &cursor);
+        *pending_fire |= input.fire;
+        *since_send += time.delta_seconds();
+        if current == AppState::Remote && *since_send >= SEND_INTERVAL {
+            *since_send = 0.0;
+            Network::send(socket, host, NetMessage::Input(NetInput {
+                movement: (input.movement.x, input.movement.y),
+                fire: *pending_fire,
+                aim: input.aim.map(|aim| (aim.x, aim.y)),
+            }));
+            *pending_fire = false;
+        } else if current != AppState::Remote && *since_send >= JOIN_INTERVAL {
+            // in the menus too, so the host doesn't take us for gone
+            *since_send = 0.0;
+            Network::send(socket, host, NetMessage::Join);
+        }
+
+        let set_scores = |scoreboard: &mut Scoreboard, players: &[(usize, usize)]| {
+            scoreboard.players = players.iter().map(|(score, health)| PlayerScore { score: *score, health: *health })
+                .collect();
+        };
+
+        if current == AppState::Remote {
+            if let Some((players, final_stats)) = finish.take() {
+                set_scores(&mut scoreboard, &players);
+                *stats = final_stats;
+                *snapshot = None;
+                state.set(AppState::Finish).unwrap();
+                return;
+            }
+            if *silence > TIMEOUT {
+                error!("lost connection to {}", host);
+                *silence = 0.0;
+                *connected = false;
+                state.set(AppState::Finish).unwrap();
+                return;
+            }
+        } else if snapshot.is_some() && (current == AppState::Start || current == AppState::Finish) {
+            // the host started a game; the player may have asked for it in the menu the same frame
+            finish.take();
+            let _ = state.set(AppState::Remote);
+            return;
+        }
+
+        let (snapshot, materials) = match (snapshot.take(), materials) {
+            (Some(snapshot), Some(materials)) if current == AppState::Remote => (snapshot, materials),
+            _ => return,
+        };
+        set_scores(&mut scoreboard, &snapshot.players);
+        timer.0.reset();
+        timer.0.set_elapsed(std::time::Duration::from_secs_f32(snapshot.elapsed));
+
+        // the sprites of the last snapshot, reused by kind
+        let mut unused: HashMap<NetKind, Vec<Entity>> = HashMap::new();
+        for (entity, remote, ..) in remote_sprites.iter_mut() {
+            unused.entry(remote.0).or_default().push(entity);
+        }
+        for sprite in snapshot.sprites.iter() {
+            let (x, y, z) = sprite.translation;
+            let (rx, ry, rz, rw) = sprite.rotation;
+            let transform = Transform {
+                translation: Vec3::new(x, y, z),
+                rotation: Quat::from_xyzw(rx, ry, rz, rw),
+                scale: Vec3::splat(sprite.scale),
+            };
+            let reused = unused.get_mut(&sprite.kind).and_then(|entities| entities.pop())
+                .and_then(|entity| remote_sprites.get_mut(entity).ok());
+            if let Some((_, _, mut old_transform, mut old_sprite, mut visible)) = reused {
+                *old_transform = transform;
+                old_sprite.size = Vec2::new(sprite.size.0, sprite.size.1);
+                old_sprite.flip_x = sprite.flip_x;
+                old_sprite.flip_y = sprite.flip_y;
+                visible.is_visible = sprite.visible;
+                continue;
+            }
+            let material = match sprite.kind {
+                NetKind::Cannon(player) => materials.cannons[player % materials.cannons.len()].clone(),
+                NetKind::Spacejunk => materials.spacejunk.clone(),
+                NetKind::Enemyship => materials.enemyship.clone(),
+                NetKind::Enemyball => params.ball_enemy_color.clone(),
+                NetKind::Selfball => params.ball_self_color.clone(),
+                NetKind::Energy => params.energy_img.clone(),
+                NetKind::Particle => materials.particle.clone(),
+            };
+            let mut entity = commands.spawn_bundle(SpriteBundle {
+                material,
+                transform,
+                sprite: Sprite {
+                    flip_x: sprite.flip_x,
+                    flip_y: sprite.flip_y,
+                    ..Sprite::new(Vec2::new(sprite.size.0, sprite.size.1))
+                },
+                visible: Visible { is_visible: sprite.visible, is_transparent: true },
+                ..Default::default()
+            });
+            entity.insert(RemoteSprite(sprite.kind)).insert(SessionEntity);
+            if let NetKind::Cannon(player) = sprite.kind {
+                // lets the camera follow the ships like on the host
+                entity.insert(Cannon::remote(player));
+            }
+        }
+        for entity in unused.into_values().flatten() {
+            commands.entity(entity).despawn();
+        }
+    }
+
+    /// Client: tells the host it quits, so another player can take its place.
+    pub fn leave(network: Res<Network>, mut exits: EventReader<AppExit>) {
+        if let (Network::Client { socket, host, connected: true, .. }, Some(_)) = (&*network, exits.iter().last()) {
+            Network::send(socket, *host, NetMessage::Leave);
+        }
+    }
+}
+
+/// The client's game screen: everything comes from the host.
+pub fn add_remote_state(appbuilder: &mut AppBuilder) -> &mut AppBuilder {
+    appbuilder
+        .add_system(Network::client_update.system().after("menu_action").after("skip_menu"))
+        .add_system_to_stage(CoreStage::Last, Network::leave.system())
+        .add_system_set(SystemSet::on_enter(AppState::Remote)
+            .with_system(setup_hud.system())
+            .with_system(GameCamera::setup.system())
+            .with_system(Crosshair::setup.system())
+            .with_system(Crosshair::hide_cursor.system())
+            .with_system(Network::setup_materials.system())
+        )
+        .add_system_set(SystemSet::on_update(AppState::Remote)
+            .with_system(Crosshair::update.system())
+            .with_system(Resolution::setup_letterbox.system())
+            .with_system(GameCamera::follow.system())
+            .with_system(scoreboard_system.system())
+        )
+        .add_system_set(SystemSet::on_exit(AppState::Remote)
+            .with_system(Crosshair::show_cursor.system())
+        )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bevy::ecs::schedule::Stage;
+    use std::time::Duration;
+
+    fn world(network: Network) -> World {
+        let mut world = World::new();
+        world.insert_resource(network);
+        world.insert_resource(Time::default());
+        world
+    }
+
+    /// Runs `Network::receive` once the datagrams sent so far have had time to arrive.
+    fn receive(world: &mut World) {
+        std::thread::sleep(Duration::from_millis(50));
+        SystemStage::single(Network::receive.system()).run(world);
+    }
+
+    fn socket(world: &World) -> &UdpSocket {
+        match world.get_resource::<Network>().unwrap() {
+            Network::Host { socket, .. } | Network::Client { socket, .. } => socket,
+            Network::Off => unreachable!(),
+        }
+    }
+
+    fn peer(world: &World) -> Option<SocketAddr> {
+        match world.get_resource::<Network>().unwrap() {
+            Network::Host { peer, .. } => *peer,
+            _ => unreachable!(),
+        }
+    }
+
+    fn sprite(kind: NetKind) -> NetSprite {
+        NetSprite {
+            kind, translation: (1.0, 2.0, 3.0), rotation: (0.0, 0.0, 0.0, 1.0), scale: 1.0, size: (30.0, 30.0),
+            flip_x: false, flip_y: false, visible: true,
+        }
+    }
+
+    /// A host and a client on this machine.
+    fn connect() -> (World, World, SocketAddr) {
+        let mut host = world(Network::host(0).unwrap());
+        host.spawn().insert(Cannon::remote(1)).insert(PlayerInput::default());
+        let port = socket(&host).local_addr().unwrap().port();
+        let mut client = world(Network::join(([127, 0, 0, 1], port).into()).unwrap());
+        let host_addr = ([127, 0, 0, 1], port).into();
+        let client_addr = ([127, 0, 0, 1], socket(&client).local_addr().unwrap().port()).into();
+
+        Network::send(socket(&client), host_addr, NetMessage::Join);
+        receive(&mut host);
+        assert_eq!(peer(&host), Some(client_addr));
+        assert!(host.get_resource::<Network>().unwrap().ready());
+        receive(&mut client);
+        assert!(matches!(client.get_resource::<Network>().unwrap(), Network::Client { connected: true, .. }));
+        (host, client, client_addr)
+    }
+
+    #[test]
+    fn loopback() {
+        let (mut host, mut client, client_addr) = connect();
+        let host_addr = ([127, 0, 0, 1], socket(&host).local_addr().unwrap().port()).into();
+
+        let input = NetInput { movement: (1.0, 0.0), fire: true, aim: Some((5.0, 6.0)) };
+        Network::send(socket(&client), host_addr, NetMessage::Input(input));
+        receive(&mut host);
+        let input = host.query::<&PlayerInput>().iter(&host).next().copied().unwrap();
+        assert_eq!(input.movement, Vec2::new(1.0, 0.0));
+        assert_eq!(input.aim, Some(Vec2::new(5.0, 6.0)));
+        assert!(input.fire);
+
+        let snapshot = Snapshot {
+            elapsed: 12.5, players: vec![(10, 3), (20, 4)], sprites: vec![sprite(NetKind::Cannon(1))],
+        };
+        Network::send(socket(&host), client_addr, NetMessage::Snapshot(snapshot));
+        receive(&mut client);
+        match client.get_resource::<Network>().unwrap() {
+            Network::Client { snapshot: Some(snapshot), .. } => {
+                assert_eq!(snapshot.elapsed, 12.5);
+                assert_eq!(snapshot.players, vec![(10, 3), (20, 4)]);
+                assert!(snapshot.sprites[0].kind == NetKind::Cannon(1));
+            }
+            _ => panic!("no snapshot"),
+        }
+
+        let mut stats = Stats::default();
+        stats.junk_kills = 7;
+        Network::send(socket(&host), client_addr, NetMessage::Finish { players: vec![(30, 0), (40, 1)], stats });
+        receive(&mut client);
+        match client.get_resource::<Network>().unwrap() {
+            Network::Client { finish: Some((players, stats)), .. } => {
+                assert_eq!(*players, vec![(30, 0), (40, 1)]);
+                assert_eq!(stats.junk_kills, 7);
+            }
+            _ => panic!("no result"),
+        }
+    }
+
+    #[test]
+    fn a_new_client_can_join_after_one_leaves() {
+        let (mut host, client, _) = connect();
+        let host_addr = ([127, 0, 0, 1], socket(&host).local_addr().unwrap().port()).into();
+        Network::send(socket(&client), host_addr, NetMessage::Leave);
+        receive(&mut host);
+        assert_eq!(peer(&host), None);
+
+        let other = UdpSocket::bind(("127.0.0.1", 0)).unwrap();
+        Network::send(&other, host_addr, NetMessage::Join);
+        receive(&mut host);
+        assert_eq!(peer(&host), Some(other.local_addr().unwrap()));
+    }
+
+    #[test]
+    fn a_silent_client_is_let_go() {
+        let (mut host, _client, _) = connect();
+        if let Network::Host { silence, .. } = &mut *host.get_resource_mut::<Network>().unwrap() {
+            *silence = TIMEOUT + 1.0;
+        }
+        receive(&mut host);
+        assert_eq!(peer(&host), None);
+        assert!(!host.get_resource::<Network>().unwrap().ready());
+    }
+
+    #[test]
+    fn encode_fits_a_datagram() {
+        let sprites = vec![sprite(NetKind::Particle); 5000];
+        let text = Network::encode(NetMessage::Snapshot(Snapshot { elapsed: 0.0, players: vec![], sprites }))
+            .unwrap();
+        assert!(text.len() <= MAX_DATAGRAM);
+        match ron::de::from_str(&text).unwrap() {
+            NetMessage::Snapshot(snapshot) => assert!(!snapshot.sprites.is_empty() && snapshot.sprites.len() < 5000),
+            _ => panic!("not a snapshot"),
+        }
+
+        // what fits is left alone
+        let sprites = vec![sprite(NetKind::Spacejunk); 10];
+        let text = Network::encode(NetMessage::Snapshot(Snapshot { elapsed: 0.0, players: vec![], sprites }))
+            .unwrap();
+        match ron::de::from_str(&text).unwrap() {
+            NetMessage::Snapshot(snapshot) => assert_eq!(snapshot.sprites.len(), 10),
+            _ => panic!("not a snapshot"),
+        }
+    }
+}
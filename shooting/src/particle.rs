@@ -1,5 +1,5 @@
 use bevy::prelude::*;
-use crate::{TIME_STEP, Params};
+use crate::{TIME_STEP, AppState, Settings};
 
 #[derive(Clone)]
 pub struct Particles {
@@ -13,6 +13,16 @@ pub struct Particle {
 }
 
 impl Particles {
+    pub fn setup(mut commands: Commands, asset_server: Res<AssetServer>,
+        mut materials: ResMut<Assets<ColorMaterial>>, settings: Res<Settings>)
+    {
+        commands.insert_resource(Particles {
+            speed: 100.0,
+            num_divide: (8.0 * settings.particle_density as f32 / 100.0).round() as usize,
+            material: materials.add(asset_server.load("images/imgbin_explosion-sprite-png.png").into())
+        });
+    }
+
     pub fn spawn(
         commands: &mut Commands,
         pos: Vec2,
@@ -45,4 +55,19 @@ impl Particles {
             }
         }
     } 
-}
\ No newline at end of file
+}
+
+/// Explosions.
+pub struct ParticlesPlugin;
+
+impl Plugin for ParticlesPlugin {
+    fn build(&self, app: &mut AppBuilder) {
+        app
+            .add_system_set(SystemSet::on_enter(AppState::InGame)
+                .with_system(Particles::setup.system())
+            )
+            .add_system_set(SystemSet::on_update(AppState::InGame)
+                .with_system(Particles::update.system())
+            );
+    }
+}
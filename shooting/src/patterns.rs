@@ -0,0 +1,227 @@
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::f32::consts::PI;
+use crate::{Balls, GameTime, Params};
+
+/// Most balls a split can burst into; every one of them can be on the field at once.
+pub const MAX_SPLIT: usize = 64;
+
+/// How the balls of a volley are laid out.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum PatternKind {
+    /// Evenly around the ship. With a `rotation` each volley is turned further: a spiral.
+    Ring,
+    /// Over `spread` degrees around the heading.
+    Fan,
+    /// In a line along the heading, `spacing` units apart, so they come one after another.
+    Burst { spacing: f32 },
+    /// Like a fan, each ball weaving `amplitude` units to the sides `frequency` times a second.
+    Sine { amplitude: f32, frequency: f32 },
+    /// Like a fan, each ball bursting into a ring of `into` balls after `delay` seconds.
+    Split { delay: f32, into: usize },
+}
+
+/// A volley of enemy shots, referenced by name from the config.
+/// Where it is fired is up to the ship's `Aim`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct BulletPattern {
+    pub kind: PatternKind,
+    pub count: usize,
+    /// Degrees, for fans, sine shots and splits.
+    pub spread: f32,
+    /// Multiplier of `enemy_ball_speed`.
+    pub speed: f32,
+    /// Degrees the pattern turns with each volley.
+    pub rotation: f32,
+}
+
+impl Default for BulletPattern {
+    fn default() -> Self {
+        BulletPattern { kind: PatternKind::Fan, count: 1, spread: 0.0, speed: 1.0, rotation: 0.0 }
+    }
+}
+
+/// Moves a ball from side to side across its path.
+pub struct Wave {
+    side: Vec2,
+    amplitude: f32,
+    frequency: f32,
+    age: f32,
+}
+
+/// Turns a ball into a ring of balls when its time is up.
+pub struct Split {
+    left: f32,
+    into: usize,
+    speed: f32,
+}
+
+pub struct Patterns;
+
+impl BulletPattern {
+    /// The patterns every config has; a config can change them or add its own.
+    pub fn builtin() -> BTreeMap<String, BulletPattern> {
+        let pattern = |kind, count, spread, speed, rotation| BulletPattern { kind, count, spread, speed, rotation };
+        let mut patterns = BTreeMap::new();
+        patterns.insert("single".to_string(), pattern(PatternKind::Fan, 1, 0.0, 1.0, 0.0));
+        patterns.insert("fan3".to_string(), pattern(PatternKind::Fan, 3, 90.0, 1.0, 0.0));
+        patterns.insert("fan5".to_string(), pattern(PatternKind::Fan, 5, 100.0, 0.8, 0.0));
+        patterns.insert("ring".to_string(), pattern(PatternKind::Ring, 12, 0.0, 0.6, 0.0));
+        patterns.insert("spiral".to_string(), pattern(PatternKind::Ring, 4, 0.0, 0.7, 17.0));
+        patterns.insert("burst".to_string(), pattern(PatternKind::Burst { spacing: 40.0 }, 4, 0.0, 1.0, 0.0));
+        patterns.insert("wave".to_string(),
+            pattern(PatternKind::Sine { amplitude: 40.0, frequency: 1.5 }, 1, 0.0, 0.8, 0.0));
+        patterns.insert("split".to_string(),
+            pattern(PatternKind::Split { delay: 0.8, into: 8 }, 1, 0.0, 0.6, 0.0));
+        patterns
+    }
+
+    pub fn validate(&self) -> Result<(), String> {
+        if self.count == 0 {
+            return Err("count must be at least 1".to_string());
+        }
+        if !(self.speed > 0.0) {
+            return Err("speed must be positive".to_string());
+        }
+        match self.kind {
+            PatternKind::Burst { spacing } if spacing < 0.0 => Err("spacing can't be negative".to_string()),
+            PatternKind::Sine { amplitude, frequency } if amplitude < 0.0 || frequency < 0.0 =>
+                Err("amplitude and frequency can't be negative".to_string()),
+            PatternKind::Split { delay, into } if !(delay > 0.0) || into == 0 || into > MAX_SPLIT =>
+                Err(format!("split needs a positive delay and 1 to {} balls", MAX_SPLIT)),
+            _ => Ok(()),
+        }
+    }
+
+    /// Directions of the balls of one volley. `volley` is how many the ship fired before.
+    pub fn directions(&self, heading: Vec2, volley: u32) -> Vec<Vec2> {
+        let base = heading.y.atan2(heading.x) + (self.rotation * volley as f32).to_radians();
+        let spread = self.spread.to_radians();
+        let count = self.count.max(1);
+        (0..count).map(|i| {
+            let angle = match self.kind {
+                PatternKind::Ring => base + PI * 2.0 * i as f32 / count as f32,
+                PatternKind::Burst { .. } => base,
+                _ if count == 1 => base,
+                _ => base - spread * 0.5 + spread * i as f32 / (count - 1) as f32,
+            };
+            Vec2::new(angle.cos(), angle.sin())
+        }).collect()
+    }
+
+    /// Fires one volley from `pos`. `speed` is the base ball speed.
+    pub fn fire(&self, commands: &mut Commands, pos: Vec2, heading: Vec2, volley: u32, speed: f32,
+        params: &Res<Params>)
+    {
+        let speed = speed * self.speed;
+        for (i, direction) in self.directions(heading, volley).into_iter().enumerate() {
+            let start = match self.kind {
+                PatternKind::Burst { spacing } => pos - direction * spacing * i as f32,
+                _ => pos,
+            };
+            let ball = Balls::spawn(commands, start, direction, speed, params);
+            match self.kind {
+                PatternKind::Sine { amplitude, frequency } => {
+                    let side = Vec2::new(-direction.y, direction.x);
+                    commands.entity(ball).insert(Wave { side, amplitude, frequency, age: 0.0 });
+                }
+                PatternKind::Split { delay, into } => {
+                    commands.entity(ball).insert(Split { left: delay, into, speed });
+                }
+                _ => {}
+            }
+        }
+    }
+}
+
+impl Patterns {
+    pub fn update(mut commands: Commands, params: Res<Params>, time: Res<GameTime>,
+        mut waves: Query<(&mut Wave, &mut Transform), Without<Split>>,
+        mut splits: Query<(Entity, &mut Split, &Transform)>)
+    {
+        let delta = time.delta().as_secs_f32();
+        for (mut wave, mut transform) in waves.iter_mut() {
+            let phase = |age: f32| (age * wave.frequency * PI * 2.0).sin() * wave.amplitude * 0.5;
+            let offset = wave.side * (phase(wave.age + delta) - phase(wave.age));
+            wave.age += delta;
+            transform.translation.x += offset.x;
+            transform.translation.y += offset.y;
+        }
+        for (entity, mut split, transform) in splits.iter_mut() {
+            split.left -= delta;
+            if split.left > 0.0 {
+                continue;
+            }
+            commands.entity(entity).despawn();
+            let ring = BulletPattern { kind: PatternKind::Ring, count: split.into, ..Default::default() };
+            ring.fire(&mut commands, transform.translation.truncate(), Vec2::X, 0, split.speed, &params);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_close(a: Vec2, b: Vec2) {
+        assert!(a.distance(b) < 1e-4, "{} != {}", a, b);
+    }
+
+    fn pattern(kind: PatternKind, count: usize, spread: f32, rotation: f32) -> BulletPattern {
+        BulletPattern { kind, count, spread, speed: 1.0, rotation }
+    }
+
+    #[test]
+    fn fan_spreads_around_the_heading() {
+        let directions = pattern(PatternKind::Fan, 3, 90.0, 0.0).directions(Vec2::X, 0);
+        assert_eq!(directions.len(), 3);
+        assert_close(directions[0], Vec2::new(1.0, -1.0).normalize());
+        assert_close(directions[1], Vec2::X);
+        assert_close(directions[2], Vec2::new(1.0, 1.0).normalize());
+
+        // a single ball goes straight, whatever the spread
+        assert_eq!(pattern(PatternKind::Fan, 1, 90.0, 0.0).directions(Vec2::Y, 0).len(), 1);
+        assert_close(pattern(PatternKind::Fan, 1, 90.0, 0.0).directions(Vec2::Y, 0)[0], Vec2::Y);
+    }
+
+    #[test]
+    fn ring_goes_all_around_and_spirals() {
+        let directions = pattern(PatternKind::Ring, 4, 0.0, 0.0).directions(Vec2::X, 0);
+        for (direction, expected) in directions.iter().zip([Vec2::X, Vec2::Y, -Vec2::X, -Vec2::Y].iter()) {
+            assert_close(*direction, *expected);
+        }
+        // each volley turns further
+        let spiral = pattern(PatternKind::Ring, 4, 0.0, 45.0);
+        assert_close(spiral.directions(Vec2::X, 2)[0], Vec2::Y);
+    }
+
+    #[test]
+    fn burst_follows_the_heading() {
+        let directions = pattern(PatternKind::Burst { spacing: 40.0 }, 3, 90.0, 0.0).directions(-Vec2::Y, 0);
+        assert!(directions.iter().all(|direction| direction.distance(-Vec2::Y) < 1e-4));
+    }
+
+    #[test]
+    fn validate() {
+        for (_, pattern) in BulletPattern::builtin() {
+            assert_eq!(pattern.validate(), Ok(()));
+        }
+        let invalid = [
+            pattern(PatternKind::Fan, 0, 0.0, 0.0),
+            BulletPattern { speed: 0.0, ..Default::default() },
+            BulletPattern { speed: f32::NAN, ..Default::default() },
+            pattern(PatternKind::Burst { spacing: -1.0 }, 3, 0.0, 0.0),
+            pattern(PatternKind::Sine { amplitude: -1.0, frequency: 1.0 }, 1, 0.0, 0.0),
+            pattern(PatternKind::Sine { amplitude: 1.0, frequency: -1.0 }, 1, 0.0, 0.0),
+            pattern(PatternKind::Split { delay: 0.0, into: 8 }, 1, 0.0, 0.0),
+            pattern(PatternKind::Split { delay: 1.0, into: 0 }, 1, 0.0, 0.0),
+            pattern(PatternKind::Split { delay: 1.0, into: MAX_SPLIT + 1 }, 1, 0.0, 0.0),
+        ];
+        for pattern in invalid.iter() {
+            assert!(pattern.validate().is_err(), "{:?}", pattern);
+        }
+        assert_eq!(pattern(PatternKind::Split { delay: 1.0, into: MAX_SPLIT }, 1, 0.0, 0.0).validate(), Ok(()));
+    }
+}
@@ -0,0 +1,145 @@
+use bevy::{
+    prelude::*,
+    input::InputSystem,
+    render::pass::ClearColor,
+};
+use rand::{rngs::StdRng, SeedableRng};
+use std::time::Duration;
+use crate::{
+    Achievements, AchievementUnlocked, AppState, Autopilot, CannonPlugin, ConfigWatcher, Console, Crosshair,
+    CursorWorld, DebugMaterials, DebugOverlay, DifficultyLevel, Enemies, EnemiesPlugin, EnemyShipsPlugin, EnergyPlugin,
+    GameCamera, GameConfig, GameEvent, GameRng, GameTime, IndicatorMaterials, Indicators, MainTimer, Network,
+    Options, ParticlesPlugin, PlayerCount, PlayerInput, Replay, Resolution, Scoreboard, Settings, MenusPlugin,
+    Stats, Telemetry, add_remote_state, cleanup_boundaries, cursor_world_system, headless_exit, headless_window,
+    game_over, scoreboard_system, setup_hud, setup_world, start_session, timer_system,
+};
+
+/// What an app embedding the game decides about it.
+#[derive(Clone, Default)]
+pub struct ShootingSettings {
+    /// What the command line sets: players, seed, difficulty, stage, headless...
+    pub options: Options,
+    /// What the options screen sets.
+    pub settings: Settings,
+    pub config: GameConfig,
+}
+
+/// The whole game, on top of bevy's `DefaultPlugins` (or the headless set) added by the app.
+/// `Achievements`, `Replay` and `Network` inserted by the app before are kept; without them the
+/// built-in achievements are used and nothing is recorded or networked.
+pub struct ShootingPlugin {
+    pub settings: ShootingSettings,
+}
+
+impl ShootingPlugin {
+    pub fn new(settings: ShootingSettings) -> Self {
+        ShootingPlugin { settings }
+    }
+}
+
+impl Plugin for ShootingPlugin {
+    fn build(&self, app: &mut AppBuilder) {
+        let ShootingSettings { options, settings, config } = self.settings.clone();
+        let seed = options.seed.unwrap_or_else(rand::random);
+        info!("random seed: {}", seed);
+        if options.headless {
+            app.add_startup_system_to_stage(StartupStage::PreStartup, headless_window.system());
+        }
+
+        app
+            .add_state(AppState::Start)
+            .insert_resource(Scoreboard::new(1, config.health))
+            .insert_resource(PlayerCount(if options.coop { 2 } else { 1 }))
+            .insert_resource(ClearColor(Color::rgb(0., 0., 0.)))
+            .insert_resource(CursorWorld(None))
+            .insert_resource(Stats::default())
+            .add_event::<GameEvent>()
+            .add_event::<AchievementUnlocked>()
+            .init_resource::<Achievements>()
+            .add_system(Achievements::show_toasts.system())
+            .add_system(Achievements::update_toasts.system())
+            .init_resource::<DebugOverlay>()
+            .init_resource::<DebugMaterials>()
+            .init_resource::<IndicatorMaterials>()
+            .add_system(DebugOverlay::toggle.system())
+            .add_system(DebugOverlay::outlines.system())
+            .add_system(DebugOverlay::update_text.system())
+            .init_resource::<Console>()
+            .add_system(Console::execute.system().after("menu_action").after("skip_menu"))
+            .add_system(Console::show.system())
+            .add_startup_system(setup_world.system())
+            .insert_resource(GameTime { delta: Duration::from_secs(0), fixed: options.headless })
+            .insert_resource(GameRng(StdRng::seed_from_u64(seed)))
+            .init_resource::<Replay>()
+            .insert_resource(Telemetry::new(options.telemetry.clone(), seed))
+            .init_resource::<Network>()
+            .insert_resource(Resolution { virtual_size: options.virtual_size.map(|(w, h)| Vec2::new(w, h)) })
+            .insert_resource(MainTimer(Timer::from_seconds(config.duration, false)))
+            .insert_resource(options.difficulty)
+            .insert_resource(DifficultyLevel(DifficultyLevel::at(config.difficulty.curve(options.difficulty), 0.0)))
+            // edits during a headless run or a recording would make it impossible to reproduce
+            .insert_resource(ConfigWatcher::new(options.config.clone()
+                .filter(|_| !options.headless && options.record.is_none() && options.playback.is_none())))
+            .add_system(ConfigWatcher::poll.system())
+            .insert_resource(config)
+            .insert_resource(options)
+            .insert_resource(settings)
+            .add_system(Resolution::resize.system())
+            .add_system_to_stage(CoreStage::PreUpdate, cursor_world_system.system().label("cursor"))
+            .add_system_to_stage(CoreStage::PreUpdate, Console::capture.system().label("console").after(InputSystem))
+            .add_system_to_stage(CoreStage::PreUpdate, PlayerInput::read_devices.system()
+                .label("input").after("cursor").after("console"))
+            .add_system_to_stage(CoreStage::PreUpdate, Autopilot::drive.system().label("bot").after("input"))
+            .add_system_to_stage(CoreStage::PreUpdate, GameTime::update.system().label("game_time"))
+            // settled before anything spawns, so the spawners don't have to wait for it
+            .add_system_to_stage(CoreStage::PreUpdate, DifficultyLevel::update.system())
+            .add_system_to_stage(CoreStage::PreUpdate, Replay::update.system()
+                .after("input").after("bot").after("game_time"))
+            .add_system_to_stage(CoreStage::PreUpdate, Network::receive.system().after("input"))
+            .add_system_set(SystemSet::on_enter(AppState::InGame)
+                .with_system(setup_hud.system())
+                .with_system(GameCamera::setup.system())
+                .with_system(Crosshair::setup.system())
+                .with_system(Crosshair::hide_cursor.system())
+                .with_system(start_session.system())
+                .with_system(Stats::reset.system())
+                .with_system(Telemetry::start.system())
+                .with_system(Console::start_session.system())
+            )
+            .add_system_set(SystemSet::on_exit(AppState::InGame)
+                .with_system(Crosshair::show_cursor.system())
+                .with_system(Replay::save.system())
+            )
+            .add_system_set(SystemSet::on_enter(AppState::Finish)
+                .with_system(Network::send_finish.system())
+                .with_system(Achievements::check_end.system())
+                .with_system(Telemetry::finish.system())
+                .with_system(headless_exit.system())
+            )
+            .add_system_set(SystemSet::on_update(AppState::InGame)
+                .with_system(Crosshair::update.system())
+                .with_system(Resolution::setup_letterbox.system())
+                .with_system(Stats::tick.system())
+                .with_system(Stats::count.system().label("stats").after("events"))
+                .with_system(Achievements::check.system().after("stats"))
+                .with_system(Telemetry::record.system().after("events"))
+                .with_system(GameCamera::follow.system().label("camera").after("cannon"))
+                .with_system(GameCamera::shake.system().after("camera").after("events"))
+                .with_system(Indicators::update.system().after("camera"))
+                .with_system(scoreboard_system.system())
+                .with_system(timer_system.system().label("timer"))
+                .with_system(game_over.system().after("timer").after("cannon"))
+                .with_system(cleanup_boundaries.system())
+                // junk bounces off the ships and shots of the other plugins, after they hit
+                .with_system(Enemies::physics.system().after("events"))
+                .with_system(Network::send_snapshot.system().after("camera"))
+            )
+            .add_plugin(MenusPlugin)
+            .add_plugin(CannonPlugin)
+            .add_plugin(EnemiesPlugin)
+            .add_plugin(EnemyShipsPlugin)
+            .add_plugin(EnergyPlugin)
+            .add_plugin(ParticlesPlugin);
+        add_remote_state(app);
+    }
+}
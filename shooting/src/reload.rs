@@ -0,0 +1,82 @@
+use bevy::prelude::*;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
+use crate::{Background, Cannon, GameConfig, MainTimer, Params};
+
+/// Seconds between two looks at the config file.
+const POLL_INTERVAL: f32 = 0.5;
+
+/// Watches the file given with `--config` and applies edits to the running game.
+pub struct ConfigWatcher {
+    path: Option<PathBuf>,
+    modified: Option<SystemTime>,
+    poll: Timer,
+}
+
+fn modified(path: &Path) -> Option<SystemTime> {
+    fs::metadata(path).and_then(|metadata| metadata.modified()).ok()
+}
+
+impl ConfigWatcher {
+    pub fn new(path: Option<PathBuf>) -> Self {
+        let modified = path.as_deref().and_then(modified);
+        ConfigWatcher { path, modified, poll: Timer::from_seconds(POLL_INTERVAL, true) }
+    }
+
+    /// Reloads the config when the file changed. An edit that doesn't load is logged and
+    /// ignored; the game keeps the last good config until the file is fixed.
+    pub fn poll(mut watcher: ResMut<ConfigWatcher>, time: Res<Time>, mut config: ResMut<GameConfig>,
+        mut params: ResMut<Params>, mut timer: ResMut<MainTimer>,
+        mut cannons: Query<(&mut Cannon, &mut Sprite), Without<Background>>,
+        mut backgrounds: Query<&mut Sprite, With<Background>>)
+    {
+        if !watcher.poll.tick(time.delta()).just_finished() {
+            return;
+        }
+        let path = match &watcher.path {
+            Some(path) => path.clone(),
+            None => return,
+        };
+        let stamp = modified(&path);
+        if stamp == watcher.modified {
+            return;
+        }
+        watcher.modified = stamp;
+
+        let new = match GameConfig::load(&path) {
+            Ok(new) => new,
+            Err(err) => {
+                warn!("{}: {}; keeping the current config", path.display(), err);
+                return;
+            }
+        };
+        let changed = config.changed_fields(&new);
+        if changed.is_empty() {
+            return;
+        }
+        info!("{} reloaded, changed: {}", path.display(), changed.join(", "));
+        if changed.contains(&"health") {
+            info!("health applies from the next game");
+        }
+
+        // most systems read the config every frame; what was copied out of it is updated here
+        let size = |(x, y): (f32, f32)| Vec2::new(x, y);
+        params.background = size(new.background);
+        params.cannon = size(new.cannon);
+        params.ball = size(new.ball);
+        params.spacejunk = size(new.spacejunk);
+        params.enemyship = size(new.enemyship);
+        params.energy = size(new.energy);
+        params.num_of_energies = new.num_of_energies;
+        for mut sprite in backgrounds.iter_mut() {
+            sprite.size = params.background;
+        }
+        for (mut cannon, mut sprite) in cannons.iter_mut() {
+            cannon.speed = new.cannon_speed;
+            sprite.size = params.cannon;
+        }
+        timer.0.set_duration(Duration::from_secs_f32(new.duration));
+        *config = new;
+    }
+}
@@ -0,0 +1,92 @@
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+use crate::{AppState, Cannon, GameTime, PlayerInput};
+
+#[derive(Serialize, Deserialize)]
+pub struct ReplayFile {
+    pub seed: u64,
+    pub frames: Vec<ReplayFrame>,
+}
+
+#[derive(Clone, Copy, Serialize, Deserialize)]
+pub struct ReplayFrame {
+    pub delta: f32,
+    pub movement: (f32, f32),
+    pub fire: bool,
+    pub aim: Option<(f32, f32)>,
+}
+
+/// Records the inputs of the first game, or feeds a recorded game back into player one's `PlayerInput`.
+#[derive(Default)]
+pub enum Replay {
+    #[default]
+    Off,
+    Record { path: PathBuf, file: ReplayFile, saved: bool },
+    Playback { file: ReplayFile, index: usize },
+}
+
+impl ReplayFile {
+    pub fn load(path: &Path) -> Result<ReplayFile, String> {
+        let text = fs::read_to_string(path).map_err(|err| err.to_string())?;
+        ron::de::from_str(&text).map_err(|err| err.to_string())
+    }
+}
+
+impl Replay {
+    pub fn update(mut replay: ResMut<Replay>, state: Res<State<AppState>>,
+        mut inputs: Query<(&Cannon, &mut PlayerInput)>, mut time: ResMut<GameTime>)
+    {
+        if *state.current() != AppState::InGame {
+            return;
+        }
+        let mut input = match inputs.iter_mut().find(|(cannon, _)| cannon.player == 0) {
+            Some((_, input)) => input,
+            None => return,
+        };
+        match &mut *replay {
+            Replay::Off => {}
+            Replay::Record { file, saved, .. } => {
+                if !*saved {
+                    file.frames.push(ReplayFrame {
+                        delta: time.delta().as_secs_f32(),
+                        movement: (input.movement.x, input.movement.y),
+                        fire: input.fire,
+                        aim: input.aim.map(|aim| (aim.x, aim.y)),
+                    });
+                }
+            }
+            Replay::Playback { file, index } => {
+                *input = match file.frames.get(*index) {
+                    Some(frame) => {
+                        time.set_delta(std::time::Duration::from_secs_f32(frame.delta));
+                        PlayerInput {
+                            movement: Vec2::new(frame.movement.0, frame.movement.1),
+                            fire: frame.fire,
+                            aim: frame.aim.map(|(x, y)| Vec2::new(x, y)),
+                        }
+                    }
+                    None => PlayerInput::default(),
+                };
+                *index += 1;
+            }
+        }
+    }
+
+    pub fn save(mut replay: ResMut<Replay>) {
+        if let Replay::Record { path, file, saved } = &mut *replay {
+            if *saved {
+                return;
+            }
+            *saved = true;
+            let result = ron::ser::to_string(file)
+                .map_err(|err| err.to_string())
+                .and_then(|text| fs::write(&path, text).map_err(|err| err.to_string()));
+            match result {
+                Ok(()) => info!("replay saved to {}", path.display()),
+                Err(err) => error!("can't save replay to {}: {}", path.display(), err),
+            }
+        }
+    }
+}
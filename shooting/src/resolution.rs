@@ -0,0 +1,96 @@
+use bevy::prelude::*;
+use bevy::render::camera::OrthographicProjection;
+use bevy::window::WindowResized;
+use crate::{Params, MainCamera};
+
+pub struct Resolution {
+    pub virtual_size: Option<Vec2>,
+}
+
+pub struct Letterbox;
+
+pub struct Hud;
+
+impl Resolution {
+    /// Visible area in world units and the letterbox bar sizes in pixels for a given window size.
+    fn fit(&self, window: Vec2) -> (Vec2, f32, Vec2) {
+        match self.virtual_size {
+            Some(size) => {
+                let scale = (size.x / window.x).max(size.y / window.y);
+                let bars = (window - size / scale) * 0.5;
+                (size, scale, bars)
+            }
+            None => (window, 1.0, Vec2::ZERO),
+        }
+    }
+
+    pub fn setup_letterbox(mut commands: Commands, mut materials: ResMut<Assets<ColorMaterial>>,
+        resolution: Res<Resolution>, camera: Query<Entity, With<MainCamera>>, bars: Query<&Letterbox>)
+    {
+        if resolution.virtual_size.is_none() || bars.iter().next().is_some() {
+            return;
+        }
+        let camera = match camera.single() {
+            Ok(camera) => camera,
+            Err(_) => return,
+        };
+        let material = materials.add(Color::rgb(0.0, 0.0, 0.0).into());
+        commands.entity(camera).with_children(|parent| {
+            for _ in 0..4 {
+                parent.spawn_bundle(SpriteBundle {
+                    material: material.clone(),
+                    transform: Transform::from_xyz(0.0, 0.0, -1.0),
+                    sprite: Sprite::new(Vec2::ZERO),
+                    ..Default::default()
+                })
+                .insert(Letterbox);
+            }
+        });
+    }
+
+    pub fn resize(mut resize_events: EventReader<WindowResized>, windows: Res<Windows>,
+        resolution: Res<Resolution>, params: Option<ResMut<Params>>,
+        added_camera: Query<Entity, Added<MainCamera>>, added_bars: Query<Entity, Added<Letterbox>>,
+        mut cameras: Query<&mut OrthographicProjection, With<MainCamera>>,
+        mut bars: Query<(&mut Sprite, &mut Transform), With<Letterbox>>,
+        mut hud: Query<&mut Style, With<Hud>>)
+    {
+        let window = match windows.get_primary() {
+            Some(window) => window,
+            None => return,
+        };
+        let resized = resize_events.iter().any(|event| event.id == window.id());
+        if !resized && added_camera.iter().next().is_none() && added_bars.iter().next().is_none() {
+            return;
+        }
+
+        let size = Vec2::new(window.width(), window.height());
+        let (visible, scale, bars_px) = resolution.fit(size);
+        if let Some(mut params) = params {
+            params.bounds = visible;
+        }
+        for mut projection in cameras.iter_mut() {
+            projection.scale = scale;
+        }
+
+        // left, right, bottom, top
+        let extent = size * scale;
+        let bar = bars_px * scale;
+        let layout = [
+            (Vec2::new(bar.x, extent.y), Vec2::new(-(visible.x + bar.x) * 0.5, 0.0)),
+            (Vec2::new(bar.x, extent.y), Vec2::new((visible.x + bar.x) * 0.5, 0.0)),
+            (Vec2::new(extent.x, bar.y), Vec2::new(0.0, -(visible.y + bar.y) * 0.5)),
+            (Vec2::new(extent.x, bar.y), Vec2::new(0.0, (visible.y + bar.y) * 0.5)),
+        ];
+        for ((mut sprite, mut transform), (bar_size, pos)) in bars.iter_mut().zip(layout.iter()) {
+            sprite.size = *bar_size;
+            transform.translation.x = pos.x;
+            transform.translation.y = pos.y;
+        }
+
+        for mut style in hud.iter_mut() {
+            style.position.top = Val::Px(20.0 + bars_px.y);
+            style.position.left = Val::Px(20.0 + bars_px.x);
+        }
+    }
+}
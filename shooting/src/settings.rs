@@ -0,0 +1,248 @@
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::Path;
+use crate::{Difficulty, Menu, MenuAction, MenuMaterials, MenuRoot, MenuSelection};
+
+pub const SETTINGS_FILE: &str = "settings.ron";
+
+const RESOLUTIONS: [(u32, u32); 5] = [(1280, 720), (1366, 768), (1600, 900), (1920, 1080), (2560, 1440)];
+const PARTICLE_DENSITIES: [u32; 4] = [0, 50, 100, 200];
+
+/// One line of the options screen.
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum Setting {
+    Resolution,
+    Fullscreen,
+    Vsync,
+    Difficulty,
+    Particles,
+    ScreenShake,
+}
+
+impl Setting {
+    const ALL: [Setting; 6] = [
+        Setting::Resolution, Setting::Fullscreen, Setting::Vsync, Setting::Difficulty,
+        Setting::Particles, Setting::ScreenShake,
+    ];
+}
+
+/// Player preferences changed on the options screen and kept in `settings.ron`
+/// between launches. Command line options take precedence over them.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Settings {
+    pub resolution: (u32, u32),
+    pub fullscreen: bool,
+    pub vsync: bool,
+    pub difficulty: Difficulty,
+    /// Percent of the default number of explosion particles.
+    pub particle_density: u32,
+    pub screen_shake: bool,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Settings {
+            resolution: (1280, 720),
+            fullscreen: false,
+            vsync: true,
+            difficulty: Difficulty::Normal,
+            particle_density: 100,
+            screen_shake: true,
+        }
+    }
+}
+
+/// The value after `current` in `values`, wrapping around; unknown values restart at the first one.
+fn next<T: Copy + PartialEq>(values: &[T], current: T) -> T {
+    match values.iter().position(|v| *v == current) {
+        Some(i) => values[(i + 1) % values.len()],
+        None => values[0],
+    }
+}
+
+impl Settings {
+    /// A missing file gives the defaults; a broken one is reported and ignored.
+    pub fn load(path: &Path) -> Settings {
+        let text = match fs::read_to_string(path) {
+            Ok(text) => text,
+            Err(_) => return Settings::default(),
+        };
+        match ron::de::from_str(&text) {
+            Ok(settings) => settings,
+            Err(err) => {
+                eprintln!("warning: ignoring {}: {}", path.display(), err);
+                Settings::default()
+            }
+        }
+    }
+
+    pub fn save(&self, path: &Path) -> Result<(), String> {
+        let text = ron::ser::to_string_pretty(self, ron::ser::PrettyConfig::default())
+            .map_err(|err| err.to_string())?;
+        fs::write(path, text).map_err(|err| err.to_string())
+    }
+
+    pub fn cycle(&mut self, setting: Setting) {
+        match setting {
+            Setting::Resolution => self.resolution = next(&RESOLUTIONS, self.resolution),
+            Setting::Fullscreen => self.fullscreen = !self.fullscreen,
+            Setting::Vsync => self.vsync = !self.vsync,
+            Setting::Difficulty => self.difficulty = next(&[Difficulty::Easy, Difficulty::Normal, Difficulty::Hard],
+                self.difficulty),
+            Setting::Particles => self.particle_density = next(&PARTICLE_DENSITIES, self.particle_density),
+            Setting::ScreenShake => self.screen_shake = !self.screen_shake,
+        }
+    }
+
+    pub fn label(&self, setting: Setting) -> String {
+        let on_off = |on: bool| if on { "On" } else { "Off" };
+        match setting {
+            Setting::Resolution => format!("Resolution: {}x{}", self.resolution.0, self.resolution.1),
+            Setting::Fullscreen => format!("Fullscreen: {}", on_off(self.fullscreen)),
+            Setting::Vsync => format!("VSync: {}", on_off(self.vsync)),
+            Setting::Difficulty => format!("Difficulty: {:?}", self.difficulty),
+            Setting::Particles => format!("Particles: {}%", self.particle_density),
+            Setting::ScreenShake => format!("Screen Shake: {}", on_off(self.screen_shake)),
+        }
+    }
+
+    fn spawn_menu(commands: &mut Commands, asset_server: &AssetServer, menu_materials: &MenuMaterials,
+        settings: &Settings)
+    {
+        let font: Handle<Font> = asset_server.load("fonts/FiraSans-Bold.ttf");
+        let labels: Vec<String> = Setting::ALL.iter().map(|setting| settings.label(*setting)).collect();
+        let mut buttons: Vec<(&str, MenuAction)> = labels.iter()
+            .zip(Setting::ALL.iter())
+            .map(|(label, setting)| (label.as_str(), MenuAction::Change(*setting)))
+            .collect();
+        buttons.push(("Back", MenuAction::Back));
+
+        Menu::spawn(commands, menu_materials, font.clone(), &buttons, |parent| {
+            parent.spawn_bundle(TextBundle {
+                style: Style { margin: Rect::all(Val::Px(20.0)), ..Default::default() },
+                text: Text::with_section(
+                    "Options",
+                    TextStyle { font, font_size: 60.0, color: Color::rgb(0.9, 0.9, 0.9) },
+                    Default::default(),
+                ),
+                ..Default::default()
+            });
+        });
+    }
+
+    pub fn enter(mut commands: Commands, asset_server: Res<AssetServer>, menu_materials: Res<MenuMaterials>,
+        settings: Res<Settings>)
+    {
+        Settings::spawn_menu(&mut commands, &asset_server, &menu_materials, &settings);
+    }
+
+    /// Changes the chosen setting and rebuilds the menu so the labels show the new values.
+    pub fn update(mut commands: Commands, mut actions: EventReader<MenuAction>, mut settings: ResMut<Settings>,
+        selection: Res<MenuSelection>, asset_server: Res<AssetServer>, menu_materials: Res<MenuMaterials>,
+        menus: Query<Entity, With<MenuRoot>>)
+    {
+        let mut changed = false;
+        for action in actions.iter() {
+            if let MenuAction::Change(setting) = action {
+                settings.cycle(*setting);
+                changed = true;
+            }
+        }
+        if !changed {
+            return;
+        }
+        for entity in menus.iter() {
+            commands.entity(entity).despawn_recursive();
+        }
+        Settings::spawn_menu(&mut commands, &asset_server, &menu_materials, &settings);
+        // keep the cursor on the line that was changed
+        commands.insert_resource(MenuSelection(selection.0));
+    }
+
+    /// Saves the settings. The window and the difficulty pick them up on the next launch, so they
+    /// don't override the command line of this one.
+    pub fn exit(settings: Res<Settings>) {
+        match settings.save(Path::new(SETTINGS_FILE)) {
+            Ok(()) => info!("settings saved to {}", SETTINGS_FILE),
+            Err(err) => error!("can't save settings to {}: {}", SETTINGS_FILE, err),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::PathBuf;
+
+    /// A file name of its own under the temp directory, removed when dropped.
+    struct TempFile(PathBuf);
+
+    impl TempFile {
+        fn new(name: &str) -> TempFile {
+            TempFile(std::env::temp_dir().join(format!("shooting-{}-{}", std::process::id(), name)))
+        }
+    }
+
+    impl Drop for TempFile {
+        fn drop(&mut self) {
+            let _ = fs::remove_file(&self.0);
+        }
+    }
+
+    #[test]
+    fn next_wraps_around() {
+        assert_eq!(next(&[1, 2, 3], 1), 2);
+        assert_eq!(next(&[1, 2, 3], 3), 1);
+        assert_eq!(next(&[1, 2, 3], 7), 1);
+    }
+
+    #[test]
+    fn cycle_goes_through_every_value() {
+        let mut settings = Settings::default();
+        settings.cycle(Setting::Resolution);
+        assert_eq!(settings.resolution, (1366, 768));
+        settings.cycle(Setting::Difficulty);
+        assert_eq!(settings.difficulty, Difficulty::Hard);
+        settings.cycle(Setting::Difficulty);
+        assert_eq!(settings.difficulty, Difficulty::Easy);
+        settings.cycle(Setting::Particles);
+        assert_eq!(settings.particle_density, 200);
+        settings.cycle(Setting::Particles);
+        assert_eq!(settings.particle_density, 0);
+        settings.cycle(Setting::Fullscreen);
+        settings.cycle(Setting::Vsync);
+        settings.cycle(Setting::ScreenShake);
+        assert!(settings.fullscreen && !settings.vsync && !settings.screen_shake);
+
+        // a hand-edited resolution goes back to the first one on the list
+        settings.resolution = (800, 600);
+        settings.cycle(Setting::Resolution);
+        assert_eq!(settings.resolution, RESOLUTIONS[0]);
+    }
+
+    #[test]
+    fn load_what_was_saved() {
+        let file = TempFile::new("settings-saved.ron");
+        let settings = Settings { resolution: (1920, 1080), difficulty: Difficulty::Hard, screen_shake: false,
+            ..Default::default() };
+        settings.save(&file.0).unwrap();
+        assert_eq!(Settings::load(&file.0), settings);
+    }
+
+    #[test]
+    fn load_falls_back_to_the_defaults() {
+        let missing = TempFile::new("settings-missing.ron");
+        assert_eq!(Settings::load(&missing.0), Settings::default());
+
+        let broken = TempFile::new("settings-broken.ron");
+        fs::write(&broken.0, "(resolution: oops").unwrap();
+        assert_eq!(Settings::load(&broken.0), Settings::default());
+
+        // fields left out keep their defaults, unknown ones from older versions are skipped
+        let partial = TempFile::new("settings-partial.ron");
+        fs::write(&partial.0, "(vsync: false, volume: 50)").unwrap();
+        assert_eq!(Settings::load(&partial.0), Settings { vsync: false, ..Default::default() });
+    }
+}
@@ -1,40 +1,48 @@
 use bevy::prelude::*;
-use crate::{Cannon, scoreboard_reset, Params, cleanup_colliders, Energy};
+use bevy::app::AppExit;
+use crate::{Params, Background, cleanup_session, GameConfig, Options, Summary, Menu, MenuAction, MenuMaterials,
+    MenuSelection, Settings, PlayerCount, Network, Achievements};
 
 #[derive(Debug, Clone, Eq, PartialEq, Hash)]
 pub enum AppState {
     Start,
+    Settings,
+    Achievements,
     InGame,
+    /// A networked game hosted by another instance.
+    Remote,
     Finish,
 }
 
-fn enter_start(
+pub fn setup_world(
     mut commands: Commands,
     asset_server: Res<AssetServer>,
     mut materials: ResMut<Assets<ColorMaterial>>,
     windows: Res<Windows>,
+    config: Res<GameConfig>,
 ) {
     commands.spawn_bundle(UiCameraBundle::default());
    
     //params
     let window = windows.get_primary().unwrap();
-    let background_size = Vec2::new(2048.0, 1536.0) * 1.5;
+    let size = |(x, y): (f32, f32)| Vec2::new(x, y);
+    let background_size = size(config.background);
     commands.insert_resource(
         Params {
             background: background_size.to_owned(),
             bounds: Vec2::new(window.width(), window.height()),
-            cannon: Vec2::new(200.0, 120.0) * 0.4,
+            cannon: size(config.cannon),
             wall: 20.0,
-            ball: Vec2::new(16.0, 16.0),
+            ball: size(config.ball),
             spacejunk_img: asset_server.load("images/space-junk.png"),
-            spacejunk: Vec2::new(250.0, 198.0),
+            spacejunk: size(config.spacejunk),
             enemyship_img: asset_server.load("images/enemy-ship.png"),
-            enemyship: Vec2::new(192.0, 250.0) * 0.3,
+            enemyship: size(config.enemyship),
             ball_self_color: materials.add(asset_server.load("images/mybullet.png").into()),
             ball_enemy_color: materials.add(asset_server.load("images/enemybullet.png").into()),
             energy_img: materials.add(asset_server.load("images/energy.png").into()),
-            num_of_energies: 5,
-            energy: Vec2::new(64.0, 64.0),
+            num_of_energies: config.num_of_energies,
+            energy: size(config.energy),
         }
     );  
 
@@ -44,85 +52,112 @@ fn enter_start(
         material: materials.add(texture_handle.into()),
         sprite: Sprite::new(background_size),
         ..Default::default()
-    });
-
-    add_button(commands, asset_server, materials, "Start", 150.0); 
+    })
+    .insert(Background);
 }
 
-fn enter_finish(
-    commands: Commands,
+fn enter_start(
+    mut commands: Commands,
     asset_server: Res<AssetServer>,
-    materials: ResMut<Assets<ColorMaterial>>
-){
-
-    add_button(commands, asset_server, materials, "GameOver", 200.0);
-}
-
-fn add_button(mut commands: Commands,asset_server: Res<AssetServer>, mut materials: ResMut<Assets<ColorMaterial>>,
-            buttontext: &str, buttonwidth: f32) {
-    commands
-        .spawn_bundle(ButtonBundle {
-            style: Style {
-                size: Size::new(Val::Px(buttonwidth), Val::Px(65.0)),
-                // center button
-                margin: Rect::all(Val::Auto),
-                // horizontally center child text
-                justify_content: JustifyContent::Center,
-                // vertically center child text
-                align_items: AlignItems::Center,
-                ..Default::default()
-            },
-            material: materials.add(Color::rgba(0.3, 0.3, 0.3, 0.5).into()),
+    menu_materials: Res<MenuMaterials>,
+) {
+    let font: Handle<Font> = asset_server.load("fonts/FiraSans-Bold.ttf");
+    Menu::spawn(&mut commands, &menu_materials, font.clone(), &[
+        ("Play", MenuAction::Play),
+        ("Co-op", MenuAction::PlayCoop),
+        ("Options", MenuAction::Settings),
+        ("Achievements", MenuAction::Achievements),
+        ("Quit", MenuAction::Quit),
+    ], |parent| {
+        parent.spawn_bundle(TextBundle {
+            style: Style { margin: Rect::all(Val::Px(30.0)), ..Default::default() },
+            text: Text::with_section(
+                "Space Shooter",
+                TextStyle { font, font_size: 80.0, color: Color::rgb(0.9, 0.9, 0.9) },
+                Default::default(),
+            ),
             ..Default::default()
-        })
-        .with_children(|parent| {
-            parent.spawn_bundle(TextBundle {
-                text: Text::with_section(
-                    buttontext,
-                    TextStyle {
-                        font: asset_server.load("fonts/FiraSans-Bold.ttf"),
-                        font_size: 40.0,
-                        color: Color::rgb(0.9, 0.9, 0.9),
-                    },
-                    Default::default(),
-                ),
-                ..Default::default()
-            });
-        });  
+        });
+    });
 }
 
-fn button(
-    mut state: ResMut<State<AppState>>,
-    mut interaction_query: Query<&Interaction, With<Button>>,
-) {
-    for interaction in interaction_query.iter_mut() {
-        match *interaction {
-            Interaction::Clicked => {
-                state.set(AppState::InGame).unwrap();
+fn menu_action(mut actions: EventReader<MenuAction>, mut state: ResMut<State<AppState>>,
+    mut players: ResMut<PlayerCount>, options: Res<Options>, network: Res<Network>, mut exit: EventWriter<AppExit>)
+{
+    // a client waits for the host to start the game
+    let play = if network.is_client() { AppState::Remote } else { AppState::InGame };
+    if let Some(action) = actions.iter().last() {
+        let next = match action {
+            MenuAction::Play => {
+                // --coop makes it a game for two
+                players.0 = if options.coop { 2 } else { 1 };
+                play
             }
-            _ => {}
-        }
+            MenuAction::PlayCoop => {
+                players.0 = 2;
+                play
+            }
+            MenuAction::Retry => play,
+            MenuAction::MainMenu | MenuAction::Back => AppState::Start,
+            MenuAction::Settings => AppState::Settings,
+            MenuAction::Achievements => AppState::Achievements,
+            MenuAction::Change(_) => return,
+            MenuAction::Quit => {
+                exit.send(AppExit);
+                return;
+            }
+        };
+        state.set(next).unwrap();
     }
 }
 
-pub fn cleanup(mut commands: Commands, mut query: Query<Entity, With<Button>>) {
-    let button_entity = query.single_mut().unwrap();
-    commands
-        .entity(button_entity)
-        .despawn_recursive();
+/// With `--stage` the menu is skipped once, at launch; "Main Menu" still shows it afterwards.
+/// A host waits for the client to join first, a client goes straight to the host's game.
+fn skip_menu(options: Res<Options>, network: Res<Network>, mut state: ResMut<State<AppState>>,
+    mut skipped: Local<bool>)
+{
+    if *skipped || !network.ready() {
+        return;
+    }
+    if network.is_client() {
+        *skipped = true;
+        state.set(AppState::Remote).unwrap();
+    } else if options.stage.is_some() {
+        *skipped = true;
+        state.set(AppState::InGame).unwrap();
+    }
 }
 
-pub fn add_other_states(appbuilder: &mut AppBuilder) -> &mut AppBuilder {
-    appbuilder
-        .add_system_set(SystemSet::on_enter(AppState::Start).with_system(enter_start.system()))
-        .add_system_set(SystemSet::on_update(AppState::Start).with_system(button.system()))
-        .add_system_set(SystemSet::on_enter(AppState::Finish).with_system(enter_finish.system()))  
-        .add_system_set(SystemSet::on_update(AppState::Finish).with_system(button.system()))
-        .add_system_set(SystemSet::on_exit(AppState::Finish)
-            .with_system(cleanup.system())
-            .with_system(cleanup_colliders.system())
-            .with_system(Cannon::reset.system())
-            .with_system(scoreboard_reset.system())
-            .with_system(Energy::setup.system())
-        )
-}
\ No newline at end of file
+/// The menus, the options and achievements screens, and the summary after a game.
+pub struct MenusPlugin;
+
+impl Plugin for MenusPlugin {
+    fn build(&self, app: &mut AppBuilder) {
+        app
+            .init_resource::<MenuMaterials>()
+            .insert_resource(MenuSelection(0))
+            .add_event::<MenuAction>()
+            .add_system(Menu::input.system().label("menu_input"))
+            .add_system(Menu::highlight.system().after("menu_input"))
+            .add_system(menu_action.system().label("menu_action").after("menu_input"))
+            .add_system_set(SystemSet::on_enter(AppState::Start).with_system(enter_start.system()))
+            .add_system_set(SystemSet::on_update(AppState::Start)
+                .with_system(skip_menu.system().label("skip_menu").before("menu_action")))
+            .add_system_set(SystemSet::on_exit(AppState::Start).with_system(Menu::cleanup.system()))
+            .add_system_set(SystemSet::on_enter(AppState::Achievements)
+                .with_system(Achievements::enter_gallery.system()))
+            .add_system_set(SystemSet::on_exit(AppState::Achievements).with_system(Menu::cleanup.system()))
+            .add_system_set(SystemSet::on_enter(AppState::Settings).with_system(Settings::enter.system()))
+            .add_system_set(SystemSet::on_update(AppState::Settings)
+                .with_system(Settings::update.system().after("menu_input")))
+            .add_system_set(SystemSet::on_exit(AppState::Settings)
+                .with_system(Menu::cleanup.system())
+                .with_system(Settings::exit.system())
+            )
+            .add_system_set(SystemSet::on_enter(AppState::Finish).with_system(Summary::enter.system()))
+            .add_system_set(SystemSet::on_exit(AppState::Finish)
+                .with_system(Menu::cleanup.system())
+                .with_system(cleanup_session.system())
+            );
+    }
+}
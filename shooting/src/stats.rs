@@ -0,0 +1,68 @@
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+use crate::{Collider, GameEvent, GameTime};
+
+/// Kills closer together than this, in seconds, keep the combo going.
+pub const COMBO_WINDOW: f32 = 2.0;
+
+#[derive(Default, Clone, Serialize, Deserialize)]
+pub struct Stats {
+    pub time_survived: f32,
+    pub junk_kills: usize,
+    pub ship_kills: usize,
+    pub shots_fired: usize,
+    pub hits: usize,
+    pub damage_taken: usize,
+    pub energies_collected: usize,
+    pub energies_spawned: usize,
+    pub combo: usize,
+    pub best_combo: usize,
+    #[serde(skip)]
+    since_last_kill: f32,
+}
+
+impl Stats {
+    pub fn accuracy(&self) -> f32 {
+        if self.shots_fired == 0 {
+            0.0
+        } else {
+            self.hits as f32 / self.shots_fired as f32 * 100.0
+        }
+    }
+
+    pub fn tick(mut stats: ResMut<Stats>, time: Res<GameTime>) {
+        let delta = time.delta().as_secs_f32();
+        stats.time_survived += delta;
+        stats.since_last_kill += delta;
+        if stats.since_last_kill > COMBO_WINDOW {
+            stats.combo = 0;
+        }
+    }
+
+    pub fn count(mut events: EventReader<GameEvent>, mut stats: ResMut<Stats>) {
+        for event in events.iter() {
+            match event {
+                GameEvent::ShotFired => stats.shots_fired += 1,
+                GameEvent::Hit => stats.hits += 1,
+                GameEvent::Killed(collider) => {
+                    match collider {
+                        Collider::Spacejunk => stats.junk_kills += 1,
+                        Collider::Enemyship => stats.ship_kills += 1,
+                        _ => {}
+                    }
+                    stats.combo += 1;
+                    stats.best_combo = stats.best_combo.max(stats.combo);
+                    stats.since_last_kill = 0.0;
+                }
+                GameEvent::DamageTaken => stats.damage_taken += 1,
+                GameEvent::EnergyCollected => stats.energies_collected += 1,
+                GameEvent::Spawned(Collider::Energy) => stats.energies_spawned += 1,
+                GameEvent::Spawned(_) => {}
+            }
+        }
+    }
+
+    pub fn reset(mut stats: ResMut<Stats>) {
+        *stats = Stats::default();
+    }
+}
@@ -0,0 +1,60 @@
+use bevy::prelude::*;
+use crate::{Scoreboard, Stats, Menu, MenuAction, MenuMaterials};
+
+pub struct Summary;
+
+impl Summary {
+    pub fn enter(mut commands: Commands, asset_server: Res<AssetServer>, menu_materials: Res<MenuMaterials>,
+        scoreboard: Res<Scoreboard>, stats: Res<Stats>)
+    {
+        let font = asset_server.load("fonts/FiraSans-Bold.ttf");
+        let mono = asset_server.load("fonts/FiraMono-Medium.ttf");
+        let label = |value: &str| TextSection {
+            value: value.to_string(),
+            style: TextStyle { font: font.clone(), font_size: 32.0, color: Color::rgb(0.5, 0.5, 1.0) },
+        };
+        let number = |value: String| TextSection {
+            value,
+            style: TextStyle { font: mono.clone(), font_size: 32.0, color: Color::rgb(1.0, 0.5, 0.5) },
+        };
+        let minutes = stats.time_survived as u32 / 60;
+        let seconds = stats.time_survived as u32 % 60;
+        let mut sections = vec![label("Score: "), number(scoreboard.score().to_string())];
+        if scoreboard.players.len() > 1 {
+            for (i, player) in scoreboard.players.iter().enumerate() {
+                sections.push(label(&format!("\nPlayer {}: ", i + 1)));
+                sections.push(number(player.score.to_string()));
+            }
+        }
+        sections.extend(vec![
+            label("\nTime survived: "), number(format!("{}:{:02}", minutes, seconds)),
+            label("\nSpace junk destroyed: "), number(stats.junk_kills.to_string()),
+            label("\nEnemy ships destroyed: "), number(stats.ship_kills.to_string()),
+            label("\nShots fired / hits: "), number(format!("{} / {}", stats.shots_fired, stats.hits)),
+            label("\nAccuracy: "), number(format!("{:.0}%", stats.accuracy())),
+            label("\nDamage taken: "), number(stats.damage_taken.to_string()),
+            label("\nEnergies collected: "), number(stats.energies_collected.to_string()),
+            label("\nBest combo: "), number(stats.best_combo.to_string()),
+        ]);
+
+        Menu::spawn(&mut commands, &menu_materials, font.clone(), &[
+            ("Retry", MenuAction::Retry),
+            ("Main Menu", MenuAction::MainMenu),
+            ("Quit", MenuAction::Quit),
+        ], |parent| {
+            parent.spawn_bundle(TextBundle {
+                text: Text::with_section(
+                    "Game Over",
+                    TextStyle { font: font.clone(), font_size: 60.0, color: Color::rgb(0.9, 0.9, 0.9) },
+                    Default::default(),
+                ),
+                ..Default::default()
+            });
+            parent.spawn_bundle(TextBundle {
+                style: Style { margin: Rect::all(Val::Px(20.0)), ..Default::default() },
+                text: Text { sections, ..Default::default() },
+                ..Default::default()
+            });
+        });
+    }
+}
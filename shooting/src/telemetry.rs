@@ -0,0 +1,153 @@
+use bevy::prelude::*;
+use serde::Serialize;
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+use crate::{AppState, Collider, Difficulty, GameEvent, GameTime, Options, Scoreboard, Stats};
+
+/// Where session logs go unless `--telemetry` says otherwise.
+pub const TELEMETRY_DIR: &str = "telemetry";
+/// One line per session, appended to in the telemetry directory.
+const SESSIONS_FILE: &str = "sessions.csv";
+
+/// Something that happened during a session, `time` seconds after it started.
+#[derive(Serialize)]
+pub struct TelemetryRecord {
+    pub time: f32,
+    pub event: &'static str,
+    pub detail: String,
+}
+
+/// How a session went, written next to its events.
+#[derive(Serialize)]
+pub struct SessionSummary {
+    /// Unix time of the start, in milliseconds.
+    pub started: u128,
+    pub seed: u64,
+    pub difficulty: Difficulty,
+    pub stage: u32,
+    pub players: usize,
+    /// Player one was flown by the autopilot.
+    pub bot: bool,
+    pub scores: Vec<usize>,
+    pub score: usize,
+    pub health: usize,
+    pub accuracy: f32,
+    pub stats: Stats,
+}
+
+#[derive(Serialize)]
+struct SessionLog<'a> {
+    summary: &'a SessionSummary,
+    events: &'a [TelemetryRecord],
+}
+
+/// Logs the gameplay events of each session and writes them out, as JSON and CSV, when it is over.
+pub struct Telemetry {
+    /// `None` when turned off with `--no-telemetry`.
+    dir: Option<PathBuf>,
+    seed: u64,
+    started: u128,
+    elapsed: f32,
+    records: Vec<TelemetryRecord>,
+}
+
+fn collider_name(collider: Collider) -> String {
+    format!("{:?}", collider).to_lowercase()
+}
+
+fn now() -> u128 {
+    SystemTime::now().duration_since(UNIX_EPOCH).map(|time| time.as_millis()).unwrap_or(0)
+}
+
+impl Telemetry {
+    pub fn new(dir: Option<PathBuf>, seed: u64) -> Self {
+        Telemetry { dir, seed, started: 0, elapsed: 0.0, records: Vec::new() }
+    }
+
+    fn push(&mut self, event: &'static str, detail: String) {
+        self.records.push(TelemetryRecord { time: self.elapsed, event, detail });
+    }
+
+    pub fn start(mut telemetry: ResMut<Telemetry>) {
+        telemetry.started = now();
+        telemetry.elapsed = 0.0;
+        telemetry.records.clear();
+        telemetry.push("state", format!("{:?}", AppState::InGame));
+    }
+
+    pub fn record(mut telemetry: ResMut<Telemetry>, time: Res<GameTime>, mut events: EventReader<GameEvent>) {
+        telemetry.elapsed += time.delta().as_secs_f32();
+        for event in events.iter() {
+            let (name, detail) = match *event {
+                GameEvent::ShotFired => ("shot_fired", String::new()),
+                GameEvent::Hit => ("hit", String::new()),
+                GameEvent::Killed(collider) => ("killed", collider_name(collider)),
+                GameEvent::DamageTaken => ("damage_taken", String::new()),
+                GameEvent::EnergyCollected => ("pickup", collider_name(Collider::Energy)),
+                GameEvent::Spawned(collider) => ("spawned", collider_name(collider)),
+            };
+            telemetry.push(name, detail);
+        }
+    }
+
+    pub fn finish(mut telemetry: ResMut<Telemetry>, options: Res<Options>, difficulty: Res<Difficulty>,
+        scoreboard: Res<Scoreboard>, stats: Res<Stats>)
+    {
+        telemetry.push("state", format!("{:?}", AppState::Finish));
+        let dir = match &telemetry.dir {
+            Some(dir) => dir,
+            None => return,
+        };
+        let summary = SessionSummary {
+            started: telemetry.started,
+            seed: telemetry.seed,
+            difficulty: *difficulty,
+            stage: options.stage.unwrap_or(1),
+            players: scoreboard.players.len(),
+            bot: options.bot,
+            scores: scoreboard.players.iter().map(|player| player.score).collect(),
+            score: scoreboard.score(),
+            health: scoreboard.health(),
+            accuracy: stats.accuracy(),
+            stats: stats.clone(),
+        };
+        match telemetry.write(dir, &summary) {
+            Ok(name) => info!("session log saved to {}", dir.join(name).display()),
+            Err(err) => error!("can't save session log to {}: {}", dir.display(), err),
+        }
+    }
+
+    /// Writes `session-<start>.json` and `session-<start>.csv` and adds a line to `sessions.csv`.
+    fn write(&self, dir: &Path, summary: &SessionSummary) -> Result<String, String> {
+        fs::create_dir_all(dir).map_err(|err| err.to_string())?;
+        let name = format!("session-{}", summary.started);
+
+        let log = SessionLog { summary, events: &self.records };
+        let json = serde_json::to_string_pretty(&log).map_err(|err| err.to_string())?;
+        fs::write(dir.join(format!("{}.json", name)), json).map_err(|err| err.to_string())?;
+
+        let mut csv = String::from("time,event,detail\n");
+        for record in self.records.iter() {
+            csv.push_str(&format!("{:.3},{},{}\n", record.time, record.event, record.detail));
+        }
+        fs::write(dir.join(format!("{}.csv", name)), csv).map_err(|err| err.to_string())?;
+
+        let sessions = dir.join(SESSIONS_FILE);
+        let header = !sessions.exists();
+        let mut file = OpenOptions::new().create(true).append(true).open(&sessions).map_err(|err| err.to_string())?;
+        if header {
+            writeln!(file, "session,seed,difficulty,stage,players,bot,score,health,time_survived,junk_kills,ship_kills,\
+                shots_fired,hits,accuracy,damage_taken,energies_collected,energies_spawned,best_combo")
+                .map_err(|err| err.to_string())?;
+        }
+        let stats = &summary.stats;
+        writeln!(file, "{},{},{:?},{},{},{},{},{},{:.1},{},{},{},{},{:.1},{},{},{},{}",
+            name, summary.seed, summary.difficulty, summary.stage, summary.players, summary.bot, summary.score,
+            summary.health, stats.time_survived, stats.junk_kills, stats.ship_kills, stats.shots_fired, stats.hits,
+            summary.accuracy, stats.damage_taken, stats.energies_collected, stats.energies_spawned, stats.best_combo)
+            .map_err(|err| err.to_string())?;
+        Ok(name)
+    }
+}
@@ -1,4 +1,4 @@
-use bevy::prelude::*;
+use bevy::{app::AppExit, prelude::*};
 
 #[derive(Debug, Clone, Eq, PartialEq, Hash)]
 enum AppState {
@@ -7,19 +7,61 @@ enum AppState {
     Finish,
 }
 
+/// What a menu button does when it is clicked or chosen with Enter.
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+enum MenuAction {
+    Play,
+    Quit,
+}
+
+/// Top node of a menu; despawning it removes the whole menu.
+struct MenuRoot;
+
+struct MenuButton {
+    action: MenuAction,
+    index: usize,
+}
+
+/// Index of the button selected with the keyboard or last hovered by the mouse.
+struct MenuSelection(usize);
+
+struct MenuMaterials {
+    normal: Handle<ColorMaterial>,
+    hovered: Handle<ColorMaterial>,
+    pressed: Handle<ColorMaterial>,
+    none: Handle<ColorMaterial>,
+}
+
+impl FromWorld for MenuMaterials {
+    fn from_world(world: &mut World) -> Self {
+        let mut materials = world.get_resource_mut::<Assets<ColorMaterial>>().unwrap();
+        MenuMaterials {
+            normal: materials.add(Color::rgb(0.15, 0.15, 0.15).into()),
+            hovered: materials.add(Color::rgb(0.25, 0.25, 0.35).into()),
+            pressed: materials.add(Color::rgb(0.35, 0.75, 0.35).into()),
+            none: materials.add(Color::NONE.into()),
+        }
+    }
+}
+
 fn main() {
     App::build()
         .add_plugins(DefaultPlugins)
         .add_state(AppState::Start)
+        .init_resource::<MenuMaterials>()
+        .insert_resource(MenuSelection(0))
+        .add_event::<MenuAction>()
+        .add_startup_system(setup_ui_camera.system())
+        .add_system(menu_input.system().label("menu_input"))
+        .add_system(menu_highlight.system().after("menu_input"))
+        .add_system(menu_action.system().after("menu_input"))
         .add_system_set(SystemSet::on_enter(AppState::Start).with_system(setup_start.system()))
-        .add_system_set(SystemSet::on_update(AppState::Start).with_system(start.system()))
         .add_system_set(SystemSet::on_exit(AppState::Start)
             .with_system(cleanup_menu.system())
             .with_system(setup_game.system())
         )
         .add_system_set(SystemSet::on_update(AppState::InGame).with_system(ingame.system()))
         .add_system_set(SystemSet::on_enter(AppState::Finish).with_system(setup_finish.system()))
-        .add_system_set(SystemSet::on_update(AppState::Finish).with_system(start.system()))
         .add_system_set(SystemSet::on_exit(AppState::Finish)
             .with_system(cleanup_menu.system())
             .with_system(reset_game.system())
@@ -27,70 +69,141 @@ fn main() {
         .run();
 }
 
-fn cleanup_menu(mut commands: Commands, mut query: Query<Entity, With<Button>>) {
-    let button_entity = query.single_mut().unwrap();
-    commands
-        .entity(button_entity)
-        .despawn_recursive();
+fn cleanup_menu(mut commands: Commands, query: Query<Entity, With<MenuRoot>>) {
+    for entity in query.iter() {
+        commands.entity(entity).despawn_recursive();
+    }
 }
 
-fn add_button(
+fn add_menu(
     mut commands: Commands,
     asset_server: Res<AssetServer>,
-    mut materials: ResMut<Assets<ColorMaterial>>,
-    title: &str,
-    width: f32,
+    materials: Res<MenuMaterials>,
+    buttons: &[(&str, MenuAction)],
 ){
+    commands.insert_resource(MenuSelection(0));
     commands
-        .spawn_bundle(ButtonBundle {
+        .spawn_bundle(NodeBundle {
             style: Style {
-                size: Size::new(Val::Px(width), Val::Px(65.0)),
-                // center button
-                margin: Rect::all(Val::Auto),
-                // horizontally center child text
+                size: Size::new(Val::Percent(100.0), Val::Percent(100.0)),
+                // buttons top to bottom
+                flex_direction: FlexDirection::ColumnReverse,
                 justify_content: JustifyContent::Center,
-                // vertically center child text
                 align_items: AlignItems::Center,
                 ..Default::default()
             },
-            material: materials.add(Color::rgb(0.15, 0.15, 0.15).into()),
+            material: materials.none.clone(),
             ..Default::default()
         })
+        .insert(MenuRoot)
         .with_children(|parent| {
-            parent.spawn_bundle(TextBundle {
-                text: Text::with_section(
-                    title,
-                    TextStyle {
-                        font: asset_server.load("fonts/FiraSans-Bold.ttf"),
-                        font_size: 40.0,
-                        color: Color::rgb(0.9, 0.9, 0.9),
-                    },
-                    Default::default(),
-                ),
-                ..Default::default()
-            });
+            for (index, (title, action)) in buttons.iter().enumerate() {
+                parent
+                    .spawn_bundle(ButtonBundle {
+                        style: Style {
+                            size: Size::new(Val::Px(200.0), Val::Px(65.0)),
+                            margin: Rect::all(Val::Px(8.0)),
+                            // horizontally center child text
+                            justify_content: JustifyContent::Center,
+                            // vertically center child text
+                            align_items: AlignItems::Center,
+                            ..Default::default()
+                        },
+                        material: materials.normal.clone(),
+                        ..Default::default()
+                    })
+                    .insert(MenuButton { action: *action, index })
+                    .with_children(|parent| {
+                        parent.spawn_bundle(TextBundle {
+                            text: Text::with_section(
+                                *title,
+                                TextStyle {
+                                    font: asset_server.load("fonts/FiraSans-Bold.ttf"),
+                                    font_size: 40.0,
+                                    color: Color::rgb(0.9, 0.9, 0.9),
+                                },
+                                Default::default(),
+                            ),
+                            ..Default::default()
+                        });
+                    });
+            }
         });
 }
 
+fn setup_ui_camera(mut commands: Commands) {
+    commands.spawn_bundle(UiCameraBundle::default());
+}
+
 fn setup_start(
-    mut commands: Commands,
+    commands: Commands,
     asset_server: Res<AssetServer>,
-    mut materials: ResMut<Assets<ColorMaterial>>,
+    materials: Res<MenuMaterials>,
 ) {
-    commands.spawn_bundle(UiCameraBundle::default());
-    add_button(commands, asset_server, materials, "Play", 150.0);
+    add_menu(commands, asset_server, materials, &[("Play", MenuAction::Play), ("Quit", MenuAction::Quit)]);
 }
 
-fn start(
-    mut state: ResMut<State<AppState>>,
-    mut interaction_query: Query<&Interaction, With<Button>>,
+fn menu_input(
+    keyboard_input: Res<Input<KeyCode>>,
+    mut selection: ResMut<MenuSelection>,
+    buttons: Query<(&MenuButton, &Interaction)>,
+    changed: Query<(&MenuButton, &Interaction), Changed<Interaction>>,
+    mut actions: EventWriter<MenuAction>,
 ) {
-    for interaction in interaction_query.iter_mut() {
+    let count = buttons.iter().count();
+    if count == 0 {
+        return;
+    }
+    if keyboard_input.just_pressed(KeyCode::Up) {
+        selection.0 = (selection.0 + count - 1) % count;
+    }
+    if keyboard_input.just_pressed(KeyCode::Down) {
+        selection.0 = (selection.0 + 1) % count;
+    }
+    selection.0 = selection.0.min(count - 1);
+
+    for (button, interaction) in changed.iter() {
         match *interaction {
-            Interaction::Clicked => {
-                state.set(AppState::InGame).unwrap();
-            }
-            _ => {}
+            Interaction::Clicked => actions.send(button.action),
+            Interaction::Hovered => selection.0 = button.index,
+            Interaction::None => {}
+        }
+    }
+    if keyboard_input.just_pressed(KeyCode::Return) {
+        if let Some((button, _)) = buttons.iter().find(|(button, _)| button.index == selection.0) {
+            actions.send(button.action);
+        }
+    }
+}
+
+fn menu_highlight(
+    selection: Res<MenuSelection>,
+    materials: Res<MenuMaterials>,
+    mut buttons: Query<(&MenuButton, &Interaction, &mut Handle<ColorMaterial>)>,
+) {
+    for (button, interaction, mut material) in buttons.iter_mut() {
+        let wanted = if *interaction == Interaction::Clicked {
+            &materials.pressed
+        } else if button.index == selection.0 {
+            &materials.hovered
+        } else {
+            &materials.normal
+        };
+        if *material != *wanted {
+            *material = wanted.clone();
+        }
+    }
+}
+
+fn menu_action(
+    mut actions: EventReader<MenuAction>,
+    mut state: ResMut<State<AppState>>,
+    mut exit: EventWriter<AppExit>,
+) {
+    if let Some(action) = actions.iter().last() {
+        match action {
+            MenuAction::Play => state.set(AppState::InGame).unwrap(),
+            MenuAction::Quit => exit.send(AppExit),
         }
     }
 }
@@ -155,9 +268,9 @@ fn ingame(mut query_text: Query<&mut Text>,  mut query_timer: Query<&mut Timer>,
 }
 
 fn setup_finish(
-    mut commands: Commands,
+    commands: Commands,
     asset_server: Res<AssetServer>,
-    mut materials: ResMut<Assets<ColorMaterial>>,
+    materials: Res<MenuMaterials>,
 ) {
-    add_button(commands, asset_server, materials, "Game Over", 200.0);
+    add_menu(commands, asset_server, materials, &[("Play Again", MenuAction::Play), ("Quit", MenuAction::Quit)]);
 }